@@ -0,0 +1,33 @@
+use std::time::Duration;
+use anyhow::{bail, Context, Result};
+use multiplayer_game_player_test::transport::{NetworkConditions, UdpRelay};
+
+const USAGE: &str = "usage: relay <listen addr> <server addr> [--latency ms] [--jitter ms] [--loss %] [--duplicate %] [--reorder %]";
+
+// sits between udp clients and the server, e.g.
+// relay 127.0.0.1:7879 127.0.0.1:7878 --latency 50 --jitter 10 --loss 5
+// and then `connect udp://127.0.0.1:7879` from the game console.
+// it only speaks udp, tcp connections get conditioned in process with ConditionedTransport
+fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let mut args = std::env::args().skip(1);
+    let (Some(listen_addr), Some(server_addr)) = (args.next(), args.next()) else { bail!(USAGE) };
+    let mut conditions = NetworkConditions::default();
+    while let Some(flag) = args.next() {
+        let value: f32 = args.next()
+            .with_context(|| format!("missing value for {flag}"))?
+            .parse()
+            .with_context(|| format!("bad value for {flag}"))?;
+        match flag.as_str() {
+            "--latency" => conditions.latency = Duration::from_secs_f32(value / 1000.0),
+            "--jitter" => conditions.jitter = Duration::from_secs_f32(value / 1000.0),
+            "--loss" => conditions.loss = value / 100.0,
+            "--duplicate" => conditions.duplication = value / 100.0,
+            "--reorder" => conditions.reordering = value / 100.0,
+            _ => bail!("unknown flag {flag}\n{USAGE}"),
+        }
+    }
+    let relay = UdpRelay::bind(&listen_addr, &server_addr, conditions)?;
+    log::info!("relaying {} to {server_addr} with {conditions:?}", relay.local_addr()?);
+    relay.run()
+}
@@ -0,0 +1,14 @@
+use multiplayer_game_player_test::server::Server;
+
+const DEFAULT_ADDR: &str = "0.0.0.0:7878";
+
+// listens for tcp and udp clients on the same port
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let addr = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_ADDR.to_string());
+    let mut server = Server::new();
+    let tcp_addr = server.listen_tcp(&addr)?;
+    let udp_addr = server.listen_udp(tcp_addr)?;
+    log::info!("listening on tcp {tcp_addr} and udp {udp_addr}");
+    server.run()
+}
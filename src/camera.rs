@@ -0,0 +1,146 @@
+use std::time::Duration;
+use cgmath::{Matrix4, Vector2};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, Buffer, BufferBindingType, BufferUsages, Queue, ShaderStages};
+
+// world units visible from the bottom to the top of the window, the whole world used to be 2 units wide
+const DEFAULT_VIEW_HEIGHT: f32 = 2.0;
+const MIN_VIEW_HEIGHT: f32 = 0.5;
+const MAX_VIEW_HEIGHT: f32 = 20.0;
+// how much one wheel notch zooms
+const ZOOM_STEP: f32 = 1.1;
+// higher catches up faster, roughly the inverse of the time it takes to close most of the gap
+const DEFAULT_FOLLOW_SPEED: f32 = 8.0;
+
+// cgmath's ortho is made for opengl's -1 to 1 depth range, wgpu wants 0 to 1
+#[rustfmt::skip]
+const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+// an orthographic camera looking at the world, keeps circles round whatever the window's shape
+pub struct Camera {
+    center: Vector2<f32>,
+    view_height: f32,
+    aspect: f32,
+    // None snaps straight to the target
+    pub follow_speed: Option<f32>,
+}
+impl Camera {
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut camera = Self {
+            center: Vector2::new(0.0, 0.0),
+            view_height: DEFAULT_VIEW_HEIGHT,
+            aspect: 1.0,
+            follow_speed: Some(DEFAULT_FOLLOW_SPEED),
+        };
+        camera.resize(width, height);
+        camera
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+
+    // moves towards `target`, framerate independent
+    pub fn follow(&mut self, target: [f32; 2], elapsed: Duration) {
+        let target = Vector2::from(target);
+        self.center = match self.follow_speed {
+            Some(speed) => self.center + (target - self.center) * (1.0 - (-speed * elapsed.as_secs_f32()).exp()),
+            None => target,
+        };
+    }
+
+    // positive zooms in
+    pub fn zoom(&mut self, notches: f32) {
+        self.view_height = (self.view_height * ZOOM_STEP.powf(-notches)).clamp(MIN_VIEW_HEIGHT, MAX_VIEW_HEIGHT);
+    }
+
+    // pixels from the top left of a window of the given size
+    pub fn world_to_screen(&self, position: [f32; 2], width: u32, height: u32) -> [f32; 2] {
+        let half_height = self.view_height / 2.0;
+        let half_width = half_height * self.aspect;
+        let x = (position[0] - self.center.x) / half_width;
+        let y = (position[1] - self.center.y) / half_height;
+        [(x + 1.0) / 2.0 * width as f32, (1.0 - y) / 2.0 * height as f32]
+    }
+
+    pub fn view_projection(&self) -> Matrix4<f32> {
+        let half_height = self.view_height / 2.0;
+        let half_width = half_height * self.aspect;
+        OPENGL_TO_WGPU_MATRIX * cgmath::ortho(
+            self.center.x - half_width,
+            self.center.x + half_width,
+            self.center.y - half_height,
+            self.center.y + half_height,
+            -1.0,
+            1.0,
+        )
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CameraUniform {
+    view_projection: [[f32; 4]; 4],
+}
+
+// the camera's side of the gpu, bound at group 0 by every pipeline that draws the world
+pub struct CameraBinding {
+    buffer: Buffer,
+    bind_group: BindGroup,
+    bind_group_layout: BindGroupLayout,
+}
+impl CameraBinding {
+    pub fn new(device: &wgpu::Device, camera: &Camera) -> Self {
+        let buffer = device.create_buffer_init(&BufferInitDescriptor{
+            label: Some("camera uniform"),
+            contents: bytemuck::cast_slice(&[CameraUniform { view_projection: camera.view_projection().into() }]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor{
+            label: Some("camera bind group layout"),
+            entries: &[BindGroupLayoutEntry{
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor{
+            label: Some("camera bind group"),
+            layout: &bind_group_layout,
+            entries: &[BindGroupEntry{
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        Self {
+            buffer,
+            bind_group,
+            bind_group_layout,
+        }
+    }
+
+    pub fn update(&self, queue: &Queue, camera: &Camera) {
+        let uniform = CameraUniform { view_projection: camera.view_projection().into() };
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    pub fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.bind_group_layout
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+use crate::protocol::Message;
+
+pub const PING_INTERVAL: Duration = Duration::from_millis(500);
+// smoothing factors, the same ones tcp uses for its rtt estimate
+const RTT_GAIN: f64 = 0.125;
+const JITTER_GAIN: f64 = 0.25;
+const OFFSET_GAIN: f64 = 0.1;
+
+// how far away the server is and what time it is there, from periodic pings.
+// all times are in milliseconds
+pub struct NetworkClock {
+    started: Instant,
+    last_ping: Option<Instant>,
+    rtt: Option<f64>,
+    jitter: f64,
+    // server time minus local time
+    offset: Option<f64>,
+}
+impl Default for NetworkClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl NetworkClock {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            last_ping: None,
+            rtt: None,
+            jitter: 0.0,
+            offset: None,
+        }
+    }
+
+    // forget everything, the next server may be somewhere else entirely
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn ping_due(&mut self, now: Instant) -> Option<Message> {
+        if self.last_ping.is_some_and(|last| now - last < PING_INTERVAL) {
+            return None;
+        }
+        self.last_ping = Some(now);
+        Some(Message::Ping { timestamp: self.local_time(now) as u64 })
+    }
+
+    pub fn on_pong(&mut self, timestamp: u64, server_time: u64, now: Instant) {
+        let local_time = self.local_time(now);
+        let sample = (local_time - timestamp as f64).max(0.0);
+        match self.rtt {
+            Some(rtt) => {
+                self.jitter += JITTER_GAIN * ((rtt - sample).abs() - self.jitter);
+                self.rtt = Some(rtt + RTT_GAIN * (sample - rtt));
+            }
+            None => {
+                self.jitter = sample / 2.0;
+                self.rtt = Some(sample);
+            }
+        }
+        // the server answered roughly half a round trip ago
+        let offset_sample = server_time as f64 + sample / 2.0 - local_time;
+        self.offset = Some(match self.offset {
+            Some(offset) => offset + OFFSET_GAIN * (offset_sample - offset),
+            None => offset_sample,
+        });
+    }
+
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtt.map(|rtt| Duration::from_secs_f64(rtt / 1000.0))
+    }
+
+    pub fn jitter(&self) -> Duration {
+        Duration::from_secs_f64(self.jitter / 1000.0)
+    }
+
+    // estimated server time right now, once a pong came back
+    pub fn server_time(&self, now: Instant) -> Option<f64> {
+        Some(self.local_time(now) + self.offset?)
+    }
+
+    fn local_time(&self, now: Instant) -> f64 {
+        now.saturating_duration_since(self.started).as_secs_f64() * 1000.0
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::{anyhow, bail, Result};
+use winit::event::{ElementState, KeyEvent};
+use winit::keyboard::KeyCode;
+
+pub const TOGGLE_KEY: KeyCode = KeyCode::Backquote;
+
+#[derive(Debug, PartialEq)]
+pub enum ConsoleCommand {
+    Connect(String),
+    // both only reach the server on the next join, so they reconnect
+    Name(String),
+    Color([u8; 3]),
+}
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("connect"), Some(host_addr), None) => Ok(ConsoleCommand::Connect(host_addr.to_string())),
+            (Some("connect"), _, _) => bail!("usage: connect <host:port>"),
+            // nicknames may have spaces in them
+            (Some("name"), Some(_), _) => match line.trim().split_once(char::is_whitespace) {
+                Some((_, nickname)) => Ok(ConsoleCommand::Name(nickname.trim().to_string())),
+                None => bail!("usage: name <nickname>"),
+            },
+            (Some("name"), None, _) => bail!("usage: name <nickname>"),
+            (Some("color"), _, _) => {
+                let channels: Vec<u8> = line.split_whitespace().skip(1)
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| anyhow!("usage: color <red> <green> <blue>, each 0 to 255"))?;
+                let color = channels.try_into().map_err(|_| anyhow!("usage: color <red> <green> <blue>, each 0 to 255"))?;
+                Ok(ConsoleCommand::Color(color))
+            }
+            (Some(command), _, _) => bail!("unknown command {command}"),
+            (None, _, _) => bail!("empty command"),
+        }
+    }
+}
+
+// a single line of text input, opened with the toggle key and submitted with enter
+#[derive(Default)]
+pub struct Console {
+    open: bool,
+    line: String,
+}
+impl Console {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.line.clear();
+    }
+
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    // returns the parsed command once the line is submitted
+    pub fn handle_key(&mut self, event: &KeyEvent) -> Option<Result<ConsoleCommand>> {
+        if event.state != ElementState::Pressed {
+            return None;
+        }
+        match event.physical_key {
+            KeyCode::Enter | KeyCode::NumpadEnter => {
+                self.open = false;
+                Some(ConsoleCommand::parse(&self.line))
+            }
+            KeyCode::Escape => {
+                self.open = false;
+                None
+            }
+            TOGGLE_KEY if !event.repeat => {
+                self.open = false;
+                None
+            }
+            KeyCode::Backspace => {
+                self.line.pop();
+                None
+            }
+            _ => {
+                if let Some(text) = &event.text {
+                    self.line.extend(text.chars().filter(|c| !c.is_control()));
+                }
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use winit::keyboard::KeyCode;
+use crate::transport::TransportStats;
+
+pub const TOGGLE_KEY: KeyCode = KeyCode::F3;
+// fps and frame time are averaged over this long, network rates are measured over it
+const SAMPLE_WINDOW: Duration = Duration::from_secs(1);
+
+// everything the overlay shows that lives somewhere else
+pub struct DebugInfo {
+    pub tick: u64,
+    pub position: [f32; 2],
+    pub rtt: Option<Duration>,
+    pub jitter: Duration,
+    pub players: usize,
+}
+
+// frame and network statistics, only shown while toggled on but always measured
+// so the numbers are already settled when it opens
+#[derive(Default)]
+pub struct DebugOverlay {
+    visible: bool,
+    frames: VecDeque<(Instant, Duration)>,
+    // network totals at the start of the current window
+    window_start: Option<(Instant, TransportStats)>,
+    // per second, over the last complete window
+    rates: TransportStats,
+}
+impl DebugOverlay {
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    // once per frame with the time since the previous one and the network totals so far
+    pub fn record_frame(&mut self, now: Instant, frame_time: Duration, network: TransportStats) {
+        self.frames.push_back((now, frame_time));
+        while self.frames.front().is_some_and(|(at, _)| now - *at > SAMPLE_WINDOW) {
+            self.frames.pop_front();
+        }
+
+        let (started, start_stats) = *self.window_start.get_or_insert((now, network));
+        let elapsed = now - started;
+        if elapsed >= SAMPLE_WINDOW {
+            let delta = network - start_stats;
+            let per_second = |count: u64| (count as f64 / elapsed.as_secs_f64()).round() as u64;
+            self.rates = TransportStats {
+                bytes_sent: per_second(delta.bytes_sent),
+                bytes_received: per_second(delta.bytes_received),
+                packets_sent: per_second(delta.packets_sent),
+                packets_received: per_second(delta.packets_received),
+            };
+            self.window_start = Some((now, network));
+        }
+    }
+
+    pub fn text(&self, info: &DebugInfo) -> String {
+        let frame_time = match self.frames.len() {
+            0 => Duration::ZERO,
+            len => self.frames.iter().map(|(_, frame_time)| *frame_time).sum::<Duration>() / len as u32,
+        };
+        let fps = if frame_time.is_zero() { 0.0 } else { 1.0 / frame_time.as_secs_f64() };
+        let mut text = String::new();
+        // writing to a String can't fail
+        let _ = writeln!(text, "fps {fps:.0} ({:.2} ms)", frame_time.as_secs_f64() * 1000.0);
+        let _ = writeln!(text, "tick {}", info.tick);
+        let _ = writeln!(text, "position {:.2}, {:.2}", info.position[0], info.position[1]);
+        match info.rtt {
+            Some(rtt) => {
+                let _ = writeln!(text, "rtt {:.1} ms (jitter {:.1} ms)", rtt.as_secs_f64() * 1000.0, info.jitter.as_secs_f64() * 1000.0);
+            }
+            None => {
+                let _ = writeln!(text, "rtt -");
+            }
+        }
+        let _ = writeln!(text, "in {} B/s, {} packets/s", self.rates.bytes_received, self.rates.packets_received);
+        let _ = writeln!(text, "out {} B/s, {} packets/s", self.rates.bytes_sent, self.rates.packets_sent);
+        let _ = write!(text, "players {}", info.players);
+        text
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::{anyhow, bail, Context, Result};
+use wgpu::{Adapter, Backends, DeviceDescriptor, PowerPreference, Queue};
+
+// same variable wgpu's own examples read
+pub const BACKEND_ENV: &str = "WGPU_BACKEND";
+pub const BACKEND_NAMES: &str = "vulkan, metal, dx12, dx11, gl, webgpu, primary, secondary, all";
+
+// how the client picks its gpu
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GraphicsConfig {
+    pub backends: Backends,
+    pub power_preference: PowerPreference,
+    // a software adapter, for machines without a usable gpu driver
+    pub force_fallback_adapter: bool,
+}
+impl Default for GraphicsConfig {
+    fn default() -> Self {
+        Self {
+            backends: Backends::PRIMARY,
+            power_preference: PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+        }
+    }
+}
+impl GraphicsConfig {
+    // the defaults with WGPU_BACKEND applied, command line flags go on top of this
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        if let Ok(names) = std::env::var(BACKEND_ENV) {
+            config.backends = parse_backends(&names).with_context(|| format!("bad {BACKEND_ENV}"))?;
+        }
+        Ok(config)
+    }
+
+    // what to say when no adapter matches, with whatever there is left to try
+    pub(crate) fn no_adapter_error(&self) -> anyhow::Error {
+        let hint = if self.force_fallback_adapter {
+            "try another backend with --backend"
+        } else {
+            "try another backend with --backend or software rendering with --fallback-adapter"
+        };
+        anyhow!("no graphics adapter found for backends {:?}, {hint}", self.backends)
+    }
+}
+
+// the window and offscreen renders both go through here so every adapter that can draw one can draw the other,
+// nothing needs optional features and software adapters rarely reach the full default limits,
+// only the texture size follows the adapter so big windows still fit
+pub(crate) async fn request_device(adapter: &Adapter) -> Result<(wgpu::Device, Queue)> {
+    let limits = if cfg!(target_arch = "wasm32") {
+        wgpu::Limits::downlevel_webgl2_defaults()
+    } else {
+        wgpu::Limits::downlevel_defaults()
+    };
+    adapter.request_device(
+        &DeviceDescriptor {
+            features: wgpu::Features::empty(),
+            limits: limits.using_resolution(adapter.limits()),
+            label: None,
+        },
+        None,
+    ).await.with_context(|| format!("{} can't create a device", adapter.get_info().name))
+}
+
+// a comma separated list like "vulkan,gl", case insensitive
+pub fn parse_backends(names: &str) -> Result<Backends> {
+    let mut backends = Backends::empty();
+    for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        backends |= match name.to_lowercase().as_str() {
+            "vulkan" | "vk" => Backends::VULKAN,
+            "metal" | "mtl" => Backends::METAL,
+            "dx12" | "d3d12" => Backends::DX12,
+            "dx11" | "d3d11" => Backends::DX11,
+            "gl" | "opengl" | "gles" => Backends::GL,
+            "webgpu" => Backends::BROWSER_WEBGPU,
+            "primary" => Backends::PRIMARY,
+            "secondary" => Backends::SECONDARY,
+            "all" => Backends::all(),
+            _ => bail!("unknown graphics backend {name:?}, expected one of {BACKEND_NAMES}"),
+        };
+    }
+    if backends.is_empty() {
+        bail!("no graphics backend given, expected one of {BACKEND_NAMES}");
+    }
+    Ok(backends)
+}
@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use wgpu::util::StagingBelt;
+use wgpu::{CommandEncoder, TextureFormat, TextureView};
+use wgpu_glyph::ab_glyph::FontArc;
+use wgpu_glyph::{GlyphBrush, GlyphBrushBuilder, HorizontalAlign, Layout, Section, Text, VerticalAlign};
+use winit::dpi::PhysicalSize;
+use crate::instance::srgb_to_linear;
+
+const FONT: &[u8] = include_bytes!("fonts/Inconsolata-Regular.ttf");
+const STAGING_BELT_CHUNK_SIZE: u64 = 1024;
+const MARGIN: f32 = 10.0;
+const NAMEPLATE_SCALE: f32 = 18.0;
+const STATUS_SCALE: f32 = 20.0;
+const MESSAGE_SCALE: f32 = 18.0;
+const OVERLAY_SCALE: f32 = 16.0;
+const MESSAGE_DURATION: Duration = Duration::from_secs(5);
+const MAX_MESSAGES: usize = 5;
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const GREY: [f32; 4] = [0.7, 0.7, 0.7, 1.0];
+
+// text drawn over the world, everything queued during a frame is drawn by `draw`
+pub struct Hud {
+    glyph_brush: GlyphBrush<()>,
+    staging_belt: StagingBelt,
+    // newest last, each with the time it was posted
+    messages: VecDeque<(String, Instant)>,
+}
+impl Hud {
+    pub fn new(device: &wgpu::Device, format: TextureFormat) -> Self {
+        let font = FontArc::try_from_slice(FONT).expect("the bundled font is valid");
+        Self {
+            glyph_brush: GlyphBrushBuilder::using_font(font).build(device, format),
+            staging_belt: StagingBelt::new(STAGING_BELT_CHUNK_SIZE),
+            messages: VecDeque::new(),
+        }
+    }
+
+    // shown in the bottom left for a few seconds
+    pub fn push_message(&mut self, message: impl Into<String>) {
+        self.messages.push_back((message.into(), Instant::now()));
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+    }
+
+    // centered on `screen_position`, in pixels from the top left
+    pub fn nameplate(&mut self, screen_position: [f32; 2], nickname: &str, color: [u8; 3]) {
+        let [r, g, b] = color.map(srgb_to_linear);
+        self.glyph_brush.queue(Section {
+            screen_position: screen_position.into(),
+            text: vec![Text::new(nickname).with_color([r, g, b, 1.0]).with_scale(NAMEPLATE_SCALE)],
+            layout: Layout::default_single_line()
+                .h_align(HorizontalAlign::Center)
+                .v_align(VerticalAlign::Bottom),
+            ..Section::default()
+        });
+    }
+
+    // top left
+    pub fn status(&mut self, text: &str) {
+        self.glyph_brush.queue(Section {
+            screen_position: (MARGIN, MARGIN),
+            text: vec![Text::new(text).with_color(WHITE).with_scale(STATUS_SCALE)],
+            ..Section::default()
+        });
+    }
+
+    // top right, for the debug overlay
+    pub fn overlay(&mut self, text: &str, size: PhysicalSize<u32>) {
+        self.glyph_brush.queue(Section {
+            screen_position: (size.width as f32 - MARGIN, MARGIN),
+            text: vec![Text::new(text).with_color(WHITE).with_scale(OVERLAY_SCALE)],
+            layout: Layout::default_wrap().h_align(HorizontalAlign::Right),
+            ..Section::default()
+        });
+    }
+
+    pub fn draw(&mut self, device: &wgpu::Device, encoder: &mut CommandEncoder, view: &TextureView, size: PhysicalSize<u32>) {
+        let now = Instant::now();
+        self.messages.retain(|(_, posted)| now - *posted < MESSAGE_DURATION);
+        let messages: Vec<&str> = self.messages.iter().map(|(message, _)| message.as_str()).collect();
+        let messages = messages.join("\n");
+        self.glyph_brush.queue(Section {
+            screen_position: (MARGIN, size.height as f32 - MARGIN),
+            bounds: (size.width as f32 - 2.0 * MARGIN, size.height as f32),
+            text: vec![Text::new(&messages).with_color(GREY).with_scale(MESSAGE_SCALE)],
+            layout: Layout::default_wrap().v_align(VerticalAlign::Bottom),
+        });
+
+        if let Err(err) = self.glyph_brush.draw_queued(device, &mut self.staging_belt, encoder, view, size.width, size.height) {
+            log::warn!("failed to draw the hud: {err}");
+        }
+        self.staging_belt.finish();
+    }
+
+    // call once the encoder passed to `draw` was submitted
+    pub fn recall(&mut self) {
+        self.staging_belt.recall();
+    }
+}
@@ -1,4 +1,35 @@
+// one player or shape as the gpu sees it, its sprite or mesh is drawn once per instance
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Instance {
-    position: [f32; 2],
+    pub position: [f32; 2],
+    pub color: [f32; 3],
+}
+impl Instance {
+    // `color` is the 8 bit srgb color players pick, shaders work in linear color
+    pub fn new(position: [f32; 2], color: [u8; 3]) -> Self {
+        Self {
+            position,
+            color: color.map(srgb_to_linear),
+        }
+    }
 
-}
\ No newline at end of file
+    // continues after the locations taken by `Vertex::desc`
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBS: &[wgpu::VertexAttribute; 2] = &wgpu::vertex_attr_array![2=>Float32x2, 3=> Float32x3];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: ATTRIBS,
+        }
+    }
+}
+
+pub(crate) fn srgb_to_linear(channel: u8) -> f32 {
+    let channel = channel as f32 / 255.0;
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
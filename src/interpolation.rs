@@ -0,0 +1,85 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+// remote players are drawn `delay` behind the newest server time, so there is
+// almost always a snapshot on each side of the time being rendered
+pub struct Interpolator<K> {
+    delay: Duration,
+    max_extrapolation: Duration,
+    buffers: HashMap<K, VecDeque<(u64, [f32; 2])>>,
+    // newest server time seen and when it arrived, used to guess the current server time
+    latest: Option<(u64, Instant)>,
+}
+impl<K: Copy + Eq + Hash> Interpolator<K> {
+    pub fn new(delay: Duration, max_extrapolation: Duration) -> Self {
+        Self {
+            delay,
+            max_extrapolation,
+            buffers: HashMap::new(),
+            latest: None,
+        }
+    }
+
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay;
+    }
+
+    pub fn push_snapshot(&mut self, server_time: u64, received_at: Instant, positions: impl IntoIterator<Item = (K, [f32; 2])>) {
+        if self.latest.is_some_and(|(latest, _)| server_time <= latest) {
+            // out of order or duplicated, the buffers already moved past it
+            return;
+        }
+        self.latest = Some((server_time, received_at));
+        let mut seen = Vec::new();
+        for (key, position) in positions {
+            self.buffers.entry(key).or_default().push_back((server_time, position));
+            seen.push(key);
+        }
+        // whoever is missing from the snapshot has left
+        self.buffers.retain(|key, _| seen.contains(key));
+    }
+
+    // `server_now` comes from the synchronized clock, until the first pong the
+    // newest snapshot stands in for it
+    pub fn positions(&mut self, now: Instant, server_now: Option<f64>) -> Vec<(K, [f32; 2])> {
+        let Some(render_time) = self.render_time(now, server_now) else { return vec![] };
+        let max_extrapolation = self.max_extrapolation.as_secs_f64() * 1000.0;
+        self.buffers.iter_mut()
+            .map(|(key, buffer)| {
+                // keep one snapshot older than the render time to interpolate from
+                while buffer.len() > 2 && (buffer[1].0 as f64) <= render_time {
+                    buffer.pop_front();
+                }
+                (*key, sample(buffer, render_time, max_extrapolation))
+            })
+            .collect()
+    }
+
+    // in server milliseconds
+    fn render_time(&self, now: Instant, server_now: Option<f64>) -> Option<f64> {
+        let (latest, received_at) = self.latest?;
+        let server_now = server_now
+            .unwrap_or_else(|| latest as f64 + now.saturating_duration_since(received_at).as_secs_f64() * 1000.0);
+        Some(server_now - self.delay.as_secs_f64() * 1000.0)
+    }
+}
+
+fn sample(buffer: &VecDeque<(u64, [f32; 2])>, render_time: f64, max_extrapolation: f64) -> [f32; 2] {
+    let (first_time, first) = buffer[0];
+    if buffer.len() == 1 || render_time <= first_time as f64 {
+        return first;
+    }
+    let next = buffer.iter().position(|(time, _)| *time as f64 >= render_time);
+    let (from, to) = match next {
+        Some(next) => (buffer[next - 1], buffer[next]),
+        None => (buffer[buffer.len() - 2], buffer[buffer.len() - 1]),
+    };
+    let span = (to.0 - from.0) as f64;
+    // past the newest snapshot this extrapolates along the last known velocity, but only so far
+    let t = ((render_time - from.0 as f64).min(span + max_extrapolation) / span) as f32;
+    [
+        from.1[0] + (to.1[0] - from.1[0]) * t,
+        from.1[1] + (to.1[1] - from.1[1]) * t,
+    ]
+}
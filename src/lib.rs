@@ -1,32 +1,73 @@
-use cgmath::prelude::*;
-use std::{iter, thread};
+use std::iter;
+use anyhow::{Context, Result};
 use std::default::Default;
-use std::ops::{Add, Mul};
+use std::path::Path;
 use std::process::exit;
-use std::sync::{Arc, Mutex};
-use image::GenericImageView;
-use rand::{Rng};
-use wgpu::{Buffer, Color, ColorWrites, DeviceDescriptor, Features, FragmentState, PipelineLayoutDescriptor, RenderPipeline, ShaderModuleDescriptor, ShaderSource, Surface, SurfaceError, VertexState};
-use wgpu::util::{DeviceExt};
+use std::time::{Duration, Instant};
+use wgpu::{Surface, SurfaceError};
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::{Window, WindowBuilder},
 };
-use wgpu_glyph::{ab_glyph, GlyphBrush, GlyphBrushBuilder, HorizontalAlign, Layout, Section, Text};
 use winit::dpi::PhysicalSize;
 use winit::event::WindowEvent::KeyboardInput;
 use winit::keyboard::{KeyCode};
-use crate::instance::Instance;
 
-mod player;
+pub mod player;
+pub mod sim;
 pub mod vertex;
-mod instance;
+pub mod instance;
+pub mod server;
+pub mod protocol;
+pub mod network;
+pub mod interpolation;
+pub mod transport;
+pub mod console;
+pub mod clock;
+pub mod camera;
+mod hud;
+mod debug;
+pub mod graphics;
+pub mod mesh;
+pub mod scene;
+pub mod sprite;
+pub mod texture;
+pub mod screenshot;
 
-use crate::player::{Player, PossibleMovements};
-use crate::vertex::Vertex;
+use crate::camera::Camera;
+use crate::console::{Console, ConsoleCommand};
+use crate::debug::{DebugInfo, DebugOverlay};
+use crate::graphics::GraphicsConfig;
+use crate::hud::Hud;
+use crate::interpolation::Interpolator;
+use crate::network::NetworkEvent;
+use crate::transport::ConnectionStatus;
+use std::collections::BTreeMap;
+use crate::player::{Player, RemotePlayer, PLAYER_RADIUS};
+use crate::protocol::PlayerInfo;
+use crate::sim::{Input, PlayerId, TICK_DURATION};
+use crate::instance::Instance;
+use crate::mesh::Shape;
+use crate::scene::{Frame, SceneRenderer, ShapeBatch, WHITE};
+use crate::screenshot::ScreenshotQueue;
 
-const HOST_ADDR: &str = "localhost:7878";
+// where the game connects unless told otherwise, `connect` in the console changes it
+pub const DEFAULT_HOST_ADDR: &str = "localhost:7878";
+const WINDOW_TITLE: &str = "super fun game";
+// the least remote players are drawn behind, jittery connections add to it
+const INTERPOLATION_DELAY: Duration = Duration::from_millis(100);
+const MAX_INTERPOLATION_DELAY: Duration = Duration::from_millis(300);
+const MAX_EXTRAPOLATION: Duration = Duration::from_millis(250);
+const DEFAULT_COLOR: [u8; 3] = [255, 0, 255];
+// touchpads scroll in pixels, this many make one mouse wheel notch
+const PIXELS_PER_NOTCH: f32 = 50.0;
+const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+const HITBOX_SHAPE: Shape = Shape::Ring { inner_radius: PLAYER_RADIUS - 0.005, outer_radius: PLAYER_RADIUS, segments: 40 };
+const HITBOX_COLOR: [u8; 3] = [0, 255, 0];
+const WINDOW_SIZE: PhysicalSize<u32> = PhysicalSize::new(600, 600);
+// how long --screenshot waits for the server to describe the world before drawing whatever it has
+const SCREENSHOT_WAIT: Duration = Duration::from_secs(2);
 
 
 struct State {
@@ -36,46 +77,48 @@ struct State {
     config: wgpu::SurfaceConfiguration,
     size: PhysicalSize<u32>,
     window: Window,
-    render_pipeline: RenderPipeline,
     player: Player,
+    host_addr: String,
+    console: Console,
 
-    pub players_position: Arc<Mutex<Box<Vec<[f32; 2]>>>>,
-    // instances: Vec<Instance>,
-    // instance_buffer: Buffer,
-    // glyph_brush: GlyphBrush<()>,
-    // staging_belt: StagingBelt,
+    pub players: BTreeMap<PlayerId, RemotePlayer>,
+    interpolator: Interpolator<PlayerId>,
+    // simulation time not yet consumed by a tick
+    accumulator: Duration,
+    last_update: Instant,
+    tick: u64,
+    // local player first, then everyone else, rebuilt every frame
+    instances: Vec<Instance>,
+    camera: Camera,
+    scene: SceneRenderer,
+    hud: Hud,
+    debug: DebugOverlay,
+    screenshots: ScreenshotQueue,
 }
 
 impl State {
-    async unsafe fn new(window: Window) -> Self {
+    async unsafe fn new(window: Window, graphics: GraphicsConfig, host_addr: &str, player_sprite: Option<&Path>) -> Result<Self> {
         let size = window.inner_size();
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::DX12,
+            backends: graphics.backends,
             dx12_shader_compiler: Default::default(),
         });
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
+        let surface = unsafe { instance.create_surface(&window) }
+            .with_context(|| format!("failed to create a window surface with backends {:?}", graphics.backends))?;
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
+                power_preference: graphics.power_preference,
                 compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
+                force_fallback_adapter: graphics.force_fallback_adapter,
             })
             .await
-            .unwrap();
-        let (device, queue) = adapter.request_device(
-            &DeviceDescriptor{
-                features: Features::POLYGON_MODE_LINE | Features::POLYGON_MODE_POINT,
-                limits: if cfg!(target_arch = "wasm32") {
-                    wgpu::Limits::downlevel_webgl2_defaults()
-                }else {
-                    wgpu::Limits::default()
-                },
-                label: None,
-            },
-            None
-        ).await.unwrap();
-
-        let player = Player::new(HOST_ADDR, &device);
+            .ok_or_else(|| graphics.no_adapter_error())?;
+        let adapter_info = adapter.get_info();
+        log::info!("rendering with {} on {:?}", adapter_info.name, adapter_info.backend);
+        let (device, queue) = crate::graphics::request_device(&adapter).await?;
+
+        let info = PlayerInfo { nickname: default_nickname(), color: DEFAULT_COLOR };
+        let player = Player::new(host_addr, info);
 
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps
@@ -83,85 +126,56 @@ impl State {
             .iter()
             .copied()
             .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
+            .or_else(|| surface_caps.formats.first().copied())
+            .with_context(|| format!("{} can't draw to this window", adapter_info.name))?;
+        // F12 copies frames out of the surface, wgpu doesn't say which surfaces allow that and asking
+        // for it where it isn't allowed is fatal, vulkan and dx12 ones can be copied, metal and gl ones can't
+        let screenshot_usage = match adapter_info.backend {
+            wgpu::Backend::Vulkan | wgpu::Backend::Dx12 => wgpu::TextureUsages::COPY_SRC,
+            _ => wgpu::TextureUsages::empty(),
+        };
+        // empty when the chosen backend can't present to this window at all
+        let present_mode = surface_caps.present_modes.first().copied()
+            .with_context(|| format!("{} on {:?} can't present to this window", adapter_info.name, adapter_info.backend))?;
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | screenshot_usage,
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: surface_caps.present_modes[0],
+            present_mode,
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
         };
         surface.configure(&device, &config);
 
-        let shader = device.create_shader_module(ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
-
-        // let staging_belt = StagingBelt::new(1024);
-        // let font = ab_glyph::FontArc::try_from_slice(include_bytes!("./ARCADECLASSIC.TTF")).unwrap();
-        // let mut glyph_brush = GlyphBrushBuilder::using_font(font).build(&device, wgpu::TextureFormat::Bgra8UnormSrgb);
-
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[player.get_bind_group_layout()],
-                push_constant_ranges: &[],
-            });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
-            },
-            fragment: Some(FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
+        let hud = Hud::new(&device, config.format);
 
+        let camera = Camera::new(size.width, size.height);
+        let scene = SceneRenderer::new(&device, &queue, config.format, &camera, player_sprite)?;
 
-        Self {
+        Ok(Self {
             surface,
             device,
             queue,
             size,
             config,
-            render_pipeline,
             window,
 
             player,
-            players_position: Arc::new(Mutex::new(Box::new(vec![]))),
-        }
+            host_addr: host_addr.to_string(),
+            console: Console::default(),
+            players: BTreeMap::new(),
+            interpolator: Interpolator::new(INTERPOLATION_DELAY, MAX_EXTRAPOLATION),
+            accumulator: Duration::ZERO,
+            last_update: Instant::now(),
+            tick: 0,
+            instances: Vec::new(),
+            camera,
+            scene,
+            hud,
+            debug: DebugOverlay::default(),
+            screenshots: ScreenshotQueue::default(),
+        })
     }
 
     pub fn window(&self) -> &Window {
@@ -174,89 +188,262 @@ impl State {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
+            self.camera.resize(new_size.width, new_size.height);
         }
     }
 
-    #[allow(unused_variables)]
     fn input(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            KeyboardInput { device_id, event, is_synthetic } => {
-                if event.physical_key == KeyCode::KeyW && event.state == ElementState::Pressed {
-                    println!("forward");
-                    self.player.input.forward = true;
-                    return true;
+        if let WindowEvent::MouseWheel { delta, .. } = event {
+            let notches = match delta {
+                MouseScrollDelta::LineDelta(_, y) => *y,
+                MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_NOTCH,
+            };
+            self.camera.zoom(notches);
+            return true;
+        }
+        if let KeyboardInput { event, .. } = event {
+            if self.console.is_open() {
+                if let Some(command) = self.console.handle_key(event) {
+                    self.run_command(command);
+                }
+                return true;
+            }
+            if event.physical_key == debug::TOGGLE_KEY && event.state == ElementState::Pressed && !event.repeat {
+                self.debug.toggle();
+                return true;
+            }
+            if event.physical_key == screenshot::CAPTURE_KEY && event.state == ElementState::Pressed && !event.repeat {
+                if self.config.usage.contains(wgpu::TextureUsages::COPY_SRC) {
+                    self.screenshots.request();
+                } else {
+                    self.hud.push_message("screenshots aren't supported with this graphics backend");
                 }
-                if event.physical_key == KeyCode::KeyS && event.state == ElementState::Pressed {
-                    println!("backward");
-                    self.player.input.backward = true;
-                    return true;
+                return true;
+            }
+            if event.physical_key == console::TOGGLE_KEY && event.state == ElementState::Pressed {
+                self.console.open();
+                // keys held when the console opens would never see their release
+                self.player.input = Input::default();
+                return true;
+            }
+            if event.physical_key == KeyCode::KeyW && event.state == ElementState::Pressed {
+                log::trace!("forward");
+                self.player.input.forward = true;
+                return true;
+            }
+            if event.physical_key == KeyCode::KeyS && event.state == ElementState::Pressed {
+                log::trace!("backward");
+                self.player.input.backward = true;
+                return true;
+            }
+            if event.physical_key == KeyCode::KeyA && event.state == ElementState::Pressed {
+                log::trace!("left");
+                self.player.input.left = true;
+                return true;
+            }
+            if event.physical_key == KeyCode::KeyD && event.state == ElementState::Pressed {
+                log::trace!("right");
+                self.player.input.right = true;
+                return true;
+            }
+            // ===============================================================================
+            if event.physical_key == KeyCode::KeyW && event.state == ElementState::Released {
+                log::trace!("stop forward");
+                self.player.input.forward = false;
+                return true;
+            }
+            if event.physical_key == KeyCode::KeyA && event.state == ElementState::Released {
+                log::trace!("stop left");
+                self.player.input.left = false;
+                return true;
+            }
+            if event.physical_key == KeyCode::KeyS && event.state == ElementState::Released {
+                log::trace!("stop backward");
+                self.player.input.backward = false;
+                return true;
+            }
+            if event.physical_key == KeyCode::KeyD && event.state == ElementState::Released {
+                log::trace!("stop right");
+                self.player.input.right = false;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn update(&mut self) {
+        let now = Instant::now();
+        let events: Vec<NetworkEvent> = self.player.network_events().collect();
+        for event in events {
+            match event {
+                NetworkEvent::Welcome { id } => {
+                    log::info!("joined as player {id}");
+                    self.player.set_id(id);
                 }
-                if event.physical_key == KeyCode::KeyA && event.state == ElementState::Pressed {
-                    println!("left");
-                    self.player.input.left = true;
-                    return true;
+                NetworkEvent::PlayerJoined { id, info } => {
+                    log::info!("{} joined", info.nickname);
+                    self.hud.push_message(format!("{} joined", info.nickname));
+                    self.players.insert(id, RemotePlayer { info, position: None });
                 }
-                if event.physical_key == KeyCode::KeyD && event.state == ElementState::Pressed {
-                    println!("right");
-                    self.player.input.right = true;
-                    return true;
+                NetworkEvent::PlayerLeft { id } => {
+                    if let Some(player) = self.players.remove(&id) {
+                        log::info!("{} left", player.info.nickname);
+                        self.hud.push_message(format!("{} left", player.info.nickname));
+                    }
                 }
-                // ===============================================================================
-                if event.physical_key == KeyCode::KeyW && event.state == ElementState::Released {
-                    println!("stop forward");
-                    self.player.input.forward = false;
-                    return true;
+                // the local player is predicted, its own entry in the snapshot is ignored
+                NetworkEvent::Snapshot { server_time, players } => {
+                    let own_id = self.player.id();
+                    let others = players.into_iter().filter(|(id, _)| Some(*id) != own_id);
+                    self.interpolator.push_snapshot(server_time, now, others);
                 }
-                if event.physical_key == KeyCode::KeyA && event.state == ElementState::Released {
-                    println!("stop left");
-                    self.player.input.left = false;
-                    return true;
+                NetworkEvent::InputAck { sequence, position } => self.player.reconcile(sequence, position),
+                NetworkEvent::Connecting => self.set_connection_status(ConnectionStatus::Connecting),
+                NetworkEvent::Connected => {
+                    log::info!("connected to {}", self.host_addr);
+                    self.hud.push_message(format!("connected to {}", self.host_addr));
+                    self.set_connection_status(ConnectionStatus::Connected);
                 }
-                if event.physical_key == KeyCode::KeyS && event.state == ElementState::Released {
-                    println!("stop backward");
-                    self.player.input.backward = false;
-                    return true;
+                NetworkEvent::Disconnected(reason) => {
+                    log::warn!("disconnected: {reason}");
+                    // failed reconnect attempts already show in the status line
+                    if self.player.connection_status() == ConnectionStatus::Connected {
+                        self.hud.push_message(format!("disconnected: {reason}"));
+                    }
+                    self.set_connection_status(ConnectionStatus::Disconnected);
                 }
-                if event.physical_key == KeyCode::KeyD && event.state == ElementState::Released {
-                    println!("stop right");
-                    self.player.input.right = false;
-                    return true;
+                NetworkEvent::Pong { timestamp, server_time } => self.player.receive_pong(timestamp, server_time),
+            }
+        }
+        self.player.ping();
+        let saved: Vec<_> = self.screenshots.finished().collect();
+        for result in saved {
+            match result {
+                Ok(path) => self.hud.push_message(format!("saved {}", path.display())),
+                Err(err) => {
+                    log::warn!("screenshot failed: {err:#}");
+                    self.hud.push_message(format!("screenshot failed: {err}"));
                 }
             }
-            _ => {
-                println!("no input");
-                self.player.add_movement(PossibleMovements::NoInput, &self.queue).expect("app crashed");
-                return false;
+        }
+
+        // the simulation advances in fixed steps no matter how often frames come in,
+        // after a long stall the missed time is dropped instead of caught up on
+        let frame_time = now - self.last_update;
+        self.debug.record_frame(now, frame_time, self.player.network_stats());
+        self.accumulator = (self.accumulator + frame_time).min(MAX_FRAME_TIME);
+        self.last_update = now;
+        while self.accumulator >= TICK_DURATION {
+            self.accumulator -= TICK_DURATION;
+            self.fixed_update();
+        }
+
+        // snapshots arriving up to two jitters late still land before they are needed
+        let delay = (INTERPOLATION_DELAY + 2 * self.player.jitter()).min(MAX_INTERPOLATION_DELAY);
+        self.interpolator.set_delay(delay);
+        for (id, position) in self.interpolator.positions(now, self.player.server_time()) {
+            if let Some(player) = self.players.get_mut(&id) {
+                player.position = Some(position);
             }
+        }
+        self.update_instances();
+        self.camera.follow(self.player.position(), frame_time);
+        // hitboxes while the debug overlay is up, untinted so every player's is the same color
+        let hitboxes: Vec<Instance> = if self.debug.is_visible() {
+            self.instances.iter().map(|instance| Instance::new(instance.position, WHITE)).collect()
+        } else {
+            Vec::new()
         };
-        println!("no input");
-        self.player.add_movement(PossibleMovements::NoInput, &self.queue).expect("app crashed");
-        false
+        let shapes = [ShapeBatch { shape: HITBOX_SHAPE, color: HITBOX_COLOR, instances: &hitboxes }];
+        self.scene.prepare(&self.device, &self.queue, &self.camera, &Frame { players: &self.instances, shapes: &shapes });
     }
 
-    fn update(&mut self) {
-        let players_clone = Arc::clone(&self.players_position);
-        let player_stream_clone = Arc::clone(&self.player.stream);
-        // thread::spawn(move||{
-        //     let mut players = players_clone.lock().unwrap();
-        //     let stream = player_stream_clone;
-        //     let new_players_position = Player::get_players_position(&stream);
-        //     **players = new_players_position;
-        // });
-        if self.player.input.forward {
-            self.player.add_movement(PossibleMovements::Forward, &self.queue).expect("gyat dayum");
+    fn update_instances(&mut self) {
+        self.instances.clear();
+        self.instances.push(Instance::new(self.player.position(), self.player.info().color));
+        self.instances.extend(self.players.values()
+            .filter_map(|player| Some(Instance::new(player.position?, player.info.color))));
+    }
+
+    // one simulation tick, the held keys are sampled exactly once
+    fn fixed_update(&mut self) {
+        self.tick += 1;
+        self.player.tick();
+    }
+
+    fn set_connection_status(&mut self, status: ConnectionStatus) {
+        if status == self.player.connection_status() {
+            return;
+        }
+        self.player.set_connection_status(status);
+        if status != ConnectionStatus::Connected {
+            // ids are only good for one session, everyone is introduced again on the next one
+            self.forget_remote_players();
         }
-        if self.player.input.backward {
-            self.player.add_movement(PossibleMovements::Backwards, &self.queue).expect("gyat dayum");
+    }
+
+    fn run_command(&mut self, command: anyhow::Result<ConsoleCommand>) {
+        match command {
+            Ok(ConsoleCommand::Connect(host_addr)) => {
+                log::info!("switching server to {host_addr}");
+                self.host_addr = host_addr;
+                self.reconnect(self.player.info().clone());
+            }
+            Ok(ConsoleCommand::Name(nickname)) => {
+                let info = PlayerInfo { nickname, ..self.player.info().clone() };
+                self.reconnect(info);
+            }
+            Ok(ConsoleCommand::Color(color)) => {
+                let info = PlayerInfo { color, ..self.player.info().clone() };
+                self.reconnect(info);
+            }
+            Err(err) => {
+                log::warn!("{err}");
+                self.hud.push_message(err.to_string());
+            }
         }
-        if self.player.input.left {
-            self.player.add_movement(PossibleMovements::Left, &self.queue).expect("gyat dayum");
+    }
+
+    // only the network session is rebuilt, every gpu resource stays as it is
+    fn reconnect(&mut self, info: PlayerInfo) {
+        self.player.change_host(&self.host_addr, info);
+        self.forget_remote_players();
+    }
+
+    fn forget_remote_players(&mut self) {
+        self.interpolator = Interpolator::new(INTERPOLATION_DELAY, MAX_EXTRAPOLATION);
+        self.players.clear();
+    }
+
+    fn queue_hud_text(&mut self) {
+        let status = match self.player.connection_status() {
+            ConnectionStatus::Connected => self.host_addr.clone(),
+            ConnectionStatus::Connecting => format!("connecting to {}...", self.host_addr),
+            ConnectionStatus::Disconnected => format!("offline, retrying {}", self.host_addr),
+        };
+        if self.console.is_open() {
+            self.hud.status(&format!("{status}\n> {}_", self.console.line()));
+        } else {
+            self.hud.status(&status);
         }
-        if self.player.input.right {
-            self.player.add_movement(PossibleMovements::Right, &self.queue).expect("gyat dayum");
+
+        if self.debug.is_visible() {
+            let text = self.debug.text(&DebugInfo {
+                tick: self.tick,
+                position: self.player.position(),
+                rtt: self.player.rtt(),
+                jitter: self.player.jitter(),
+                players: self.players.len() + 1,
+            });
+            self.hud.overlay(&text, self.size);
         }
-        if !self.player.input.input() {
-            self.player.add_movement(PossibleMovements::NoInput, &self.queue).expect("gyat dayum");
+
+        let local = (self.player.position(), self.player.info());
+        let remote = self.players.values().filter_map(|player| Some((player.position?, &player.info)));
+        for (position, info) in iter::once(local).chain(remote) {
+            let above = [position[0], position[1] + PLAYER_RADIUS];
+            let screen_position = self.camera.world_to_screen(above, self.size.width, self.size.height);
+            self.hud.nameplate(screen_position, &info.nickname, info.color);
         }
     }
 
@@ -272,77 +459,65 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-            let (vertex_buffer, index_buffer, num_indices, bind_group) = self.player.get_buffers();
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, bind_group, &[]);
-            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..num_indices, 0, 0..1);
-        }
+        self.scene.draw(&mut encoder, &view);
+        // text goes on top of the world in a pass of its own
+        self.queue_hud_text();
+        self.hud.draw(&self.device, &mut encoder, &view, self.size);
+        let screenshot = self.screenshots.capture(&self.device, &mut encoder, &output.texture);
         self.queue.submit(iter::once(encoder.finish()));
+        self.hud.recall();
+        if let Some(readback) = screenshot {
+            self.screenshots.save(readback);
+        }
+        // runs the callbacks of finished readbacks without waiting for anything
+        self.device.poll(wgpu::Maintain::Poll);
         output.present();
 
         Ok(())
     }
 }
 
-pub async fn run() {
-    let event_loop = EventLoop::new().unwrap();
-    let mut size = PhysicalSize::new(600u32, 600);
+// the login name is a better guess than nothing, `name` in the console changes it
+fn default_nickname() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "player".to_string())
+}
 
+pub async fn run(graphics: GraphicsConfig, host_addr: &str, player_sprite: Option<&Path>) -> Result<()> {
+    let event_loop = EventLoop::new().context("failed to create the event loop")?;
     let window = WindowBuilder::new()
-        .with_title("super fun game")
-        .with_inner_size(size)
+        .with_title(WINDOW_TITLE)
+        .with_inner_size(WINDOW_SIZE)
         .build(&event_loop)
-        .unwrap();
+        .context("failed to open a window")?;
     window.set_resizable(true);
 
-    let mut state = unsafe { State::new(window) }.await;
+    let mut state = unsafe { State::new(window, graphics, host_addr, player_sprite) }.await?;
     event_loop.run(move |event, _, control_flow| {
         match event {
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == state.window().id() => {
-                if !state.input(event) {
-                    match event {
-                        WindowEvent::CloseRequested => {
-                            *control_flow = ControlFlow::Exit;
-                            exit(0);
-                        }
-                        KeyboardInput {
-                            event: KeyEvent{
-                                physical_key: KeyCode::Escape, state: ElementState::Pressed, ..
-                            },
-                            ..
-                        } => {
-                            *control_flow = ControlFlow::Exit;
-                            exit(0)
+            } if window_id == state.window().id() && !state.input(event) => {
+                match event {
+                    WindowEvent::CloseRequested => {
+                        *control_flow = ControlFlow::Exit;
+                        exit(0);
+                    }
+                    KeyboardInput {
+                        event: KeyEvent{
+                            physical_key: KeyCode::Escape, state: ElementState::Pressed, ..
                         },
-                        WindowEvent::Resized(physical_size) => {
-                            state.resize(*physical_size);
-                            size = *physical_size;
-                        }
-                        _ => {}
+                        ..
+                    } => {
+                        *control_flow = ControlFlow::Exit;
+                        exit(0)
+                    },
+                    WindowEvent::Resized(physical_size) => {
+                        state.resize(*physical_size);
                     }
+                    _ => {}
                 }
             }
             Event::RedrawRequested(window_id) if window_id == state.window().id() => {
@@ -361,5 +536,52 @@ pub async fn run() {
             _ => {}
         }
         state.window.request_redraw();
-    }).expect("TODO: panic message");
-}
\ No newline at end of file
+    }).context("the event loop stopped")
+}
+
+// one frame of the world as the server sees it, written to `path` without opening a window, for bug reports
+pub async fn screenshot(graphics: GraphicsConfig, host_addr: &str, path: &Path, player_sprite: Option<&Path>) -> Result<()> {
+    let (device, queue) = screenshot::headless_device(&graphics).await?;
+    let player = Player::new(host_addr, PlayerInfo { nickname: default_nickname(), color: DEFAULT_COLOR });
+    let mut id = None;
+    let mut infos = BTreeMap::new();
+    let mut snapshot = None;
+    let deadline = Instant::now() + SCREENSHOT_WAIT;
+    while snapshot.is_none() && Instant::now() < deadline {
+        for event in player.network_events() {
+            match event {
+                NetworkEvent::Welcome { id: own_id } => id = Some(own_id),
+                NetworkEvent::PlayerJoined { id, info } => {
+                    infos.insert(id, info);
+                }
+                NetworkEvent::PlayerLeft { id } => {
+                    infos.remove(&id);
+                }
+                // the first one after the welcome has everyone who was announced with it
+                NetworkEvent::Snapshot { players, .. } if id.is_some() => snapshot = Some(players),
+                _ => {}
+            }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let players = snapshot.unwrap_or_else(|| {
+        log::warn!("no snapshot from {host_addr}, only drawing the local player");
+        Vec::new()
+    });
+
+    let own_position = players.iter()
+        .find(|(player_id, _)| Some(*player_id) == id)
+        .map_or([0.0, 0.0], |(_, position)| *position);
+    let mut instances = vec![Instance::new(own_position, player.info().color)];
+    instances.extend(players.iter()
+        .filter(|(player_id, _)| Some(*player_id) != id)
+        .filter_map(|(player_id, position)| Some(Instance::new(*position, infos.get(player_id)?.color))));
+
+    let mut camera = Camera::new(WINDOW_SIZE.width, WINDOW_SIZE.height);
+    camera.follow_speed = None;
+    camera.follow(own_position, Duration::ZERO);
+    let image = screenshot::render_offscreen(&device, &queue, &camera, &Frame { players: &instances, shapes: &[] }, player_sprite, WINDOW_SIZE.width, WINDOW_SIZE.height)?;
+    screenshot::save_png(&image, path)?;
+    log::info!("saved {}", path.display());
+    Ok(())
+}
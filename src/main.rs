@@ -1,12 +1,36 @@
-use std::io::{Write};
-use std::net::TcpStream;
+use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use multiplayer_game_player_test::graphics::{self, GraphicsConfig, BACKEND_NAMES};
+use multiplayer_game_player_test::{run, screenshot, DEFAULT_HOST_ADDR};
 
-use multiplayer_game_player_test::run;
-fn main() {
-    // let mut stream = TcpStream::connect("5.tcp.eu.ngrok.io:14302").unwrap();
-    // let buffer: [u8; 8] = [0; 8];
-    // loop {
-    //     stream.write(&buffer).unwrap();
-    // }
-    pollster::block_on(run());
+const USAGE: &str = "usage: multiplayer_game_player_test [--host <host:port>] [--backend <names>] [--fallback-adapter] [--screenshot <path>] [--player-sprite <png>]";
+
+// --host is the server to join, udp://host:port for udp
+// --screenshot draws one frame of the game on that server offscreen and exits instead of opening a window
+// --player-sprite draws players with a png of your own
+// the backend comes from --backend, then WGPU_BACKEND, then the platform's primary ones
+fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let mut graphics = GraphicsConfig::from_env()?;
+    let mut host_addr = DEFAULT_HOST_ADDR.to_string();
+    let mut screenshot_path = None;
+    let mut player_sprite = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--host" => host_addr = args.next().with_context(|| format!("missing address for {flag}"))?,
+            "--backend" => {
+                let names = args.next().with_context(|| format!("missing value for {flag}, expected one of {BACKEND_NAMES}"))?;
+                graphics.backends = graphics::parse_backends(&names)?;
+            }
+            "--fallback-adapter" => graphics.force_fallback_adapter = true,
+            "--player-sprite" => player_sprite = Some(PathBuf::from(args.next().with_context(|| format!("missing path for {flag}"))?)),
+            "--screenshot" => screenshot_path = Some(PathBuf::from(args.next().with_context(|| format!("missing path for {flag}"))?)),
+            _ => bail!("unknown flag {flag}\n{USAGE}"),
+        }
+    }
+    match screenshot_path {
+        Some(path) => pollster::block_on(screenshot(graphics, &host_addr, &path, player_sprite.as_deref())),
+        None => pollster::block_on(run(graphics, &host_addr, player_sprite.as_deref())),
+    }
 }
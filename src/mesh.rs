@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, TAU};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{Buffer, BufferUsages, RenderPass};
+use crate::instance::srgb_to_linear;
+use crate::vertex::Vertex;
+
+// centered on the origin in world units, everything is wound counter clockwise like the pipeline expects
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    Circle { radius: f32, segments: u16 },
+    Rect { width: f32, height: f32 },
+    // with a corner pointing up
+    Polygon { radius: f32, sides: u16 },
+    Ring { inner_radius: f32, outer_radius: f32, segments: u16 },
+    // pointing along +x, the head is as long as it is wide
+    Arrow { length: f32, width: f32 },
+}
+impl Shape {
+    pub fn mesh(&self, color: [u8; 3]) -> MeshData {
+        let color = color.map(srgb_to_linear);
+        match *self {
+            Shape::Circle { radius, segments } => fan(radius, segments.max(3), 0.0, color),
+            Shape::Polygon { radius, sides } => fan(radius, sides.max(3), FRAC_PI_2, color),
+            Shape::Rect { width, height } => {
+                let (x, y) = (width / 2.0, height / 2.0);
+                MeshData {
+                    vertices: [[-x, -y], [x, -y], [x, y], [-x, y]].map(|position| vertex(position, color)).to_vec(),
+                    indices: vec![0, 1, 2, 0, 2, 3],
+                }
+            }
+            Shape::Ring { inner_radius, outer_radius, segments } => {
+                // two vertices per segment, all of them have to fit a u16 index
+                let segments = segments.clamp(3, u16::MAX / 2);
+                let vertices = (0..segments)
+                    .flat_map(|i| {
+                        let [x, y] = unit_circle(i, segments, 0.0);
+                        [[x * inner_radius, y * inner_radius], [x * outer_radius, y * outer_radius]]
+                    })
+                    .map(|position| vertex(position, color))
+                    .collect();
+                // inner and outer vertices alternate, each segment is a quad between two spokes
+                let indices = (0..segments)
+                    .flat_map(|i| {
+                        let (inner, outer) = (2 * i, 2 * i + 1);
+                        let (next_inner, next_outer) = (2 * ((i + 1) % segments), 2 * ((i + 1) % segments) + 1);
+                        [inner, outer, next_outer, inner, next_outer, next_inner]
+                    })
+                    .collect();
+                MeshData { vertices, indices }
+            }
+            Shape::Arrow { length, width } => {
+                let tip = length / 2.0;
+                let head_start = tip - width.min(length);
+                let tail = -tip;
+                let (shaft, head) = (width / 6.0, width / 2.0);
+                let vertices = [
+                    [tail, -shaft], [head_start, -shaft], [head_start, shaft], [tail, shaft],
+                    [head_start, -head], [tip, 0.0], [head_start, head],
+                ];
+                MeshData {
+                    vertices: vertices.map(|position| vertex(position, color)).to_vec(),
+                    indices: vec![0, 1, 2, 0, 2, 3, 4, 5, 6],
+                }
+            }
+        }
+    }
+
+    // floats don't hash, their bits do
+    fn key(&self, color: [u8; 3]) -> MeshKey {
+        let (kind, params) = match *self {
+            Shape::Circle { radius, segments } => (0, [radius.to_bits(), segments.into(), 0]),
+            Shape::Rect { width, height } => (1, [width.to_bits(), height.to_bits(), 0]),
+            Shape::Polygon { radius, sides } => (2, [radius.to_bits(), sides.into(), 0]),
+            Shape::Ring { inner_radius, outer_radius, segments } => (3, [inner_radius.to_bits(), outer_radius.to_bits(), segments.into()]),
+            Shape::Arrow { length, width } => (4, [length.to_bits(), width.to_bits(), 0]),
+        };
+        MeshKey { kind, params, color }
+    }
+}
+
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>,
+}
+
+// a center vertex and one on the rim per side
+fn fan(radius: f32, sides: u16, start_angle: f32, color: [f32; 3]) -> MeshData {
+    let mut vertices = vec![vertex([0.0, 0.0], color)];
+    vertices.extend((0..sides).map(|i| {
+        let [x, y] = unit_circle(i, sides, start_angle);
+        vertex([x * radius, y * radius], color)
+    }));
+    let indices = (0..sides).flat_map(|i| [0, i + 1, (i + 1) % sides + 1]).collect();
+    MeshData { vertices, indices }
+}
+
+fn unit_circle(i: u16, steps: u16, start_angle: f32) -> [f32; 2] {
+    let angle = start_angle + TAU * i as f32 / steps as f32;
+    [angle.cos(), angle.sin()]
+}
+
+fn vertex([x, y]: [f32; 2], color: [f32; 3]) -> Vertex {
+    Vertex { position: [x, y, 0.0], color }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct MeshKey {
+    kind: u8,
+    params: [u32; 3],
+    color: [u8; 3],
+}
+
+// a shape's buffers, shared by everything drawn with that shape and color
+pub struct GpuMesh {
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    num_indices: u32,
+}
+impl GpuMesh {
+    pub fn new(device: &wgpu::Device, mesh: &MeshData) -> Self {
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor{
+            label: Some("Vertex buffer"),
+            contents: bytemuck::cast_slice(mesh.vertices.as_slice()),
+            usage: BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&BufferInitDescriptor{
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(mesh.indices.as_slice()),
+            usage: BufferUsages::INDEX,
+        });
+        Self {
+            vertex_buffer,
+            index_buffer,
+            num_indices: mesh.indices.len() as u32,
+        }
+    }
+
+    // vertices go in slot 0, instances are up to the caller
+    pub fn bind<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+    }
+
+    pub fn num_indices(&self) -> u32 {
+        self.num_indices
+    }
+}
+
+// handed out by `MeshCache`, cheap to keep around
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MeshId(usize);
+
+// each shape and color is uploaded once, the first time it's asked for
+#[derive(Default)]
+pub struct MeshCache {
+    meshes: Vec<GpuMesh>,
+    ids: HashMap<MeshKey, MeshId>,
+}
+impl MeshCache {
+    pub fn get_or_create(&mut self, device: &wgpu::Device, shape: Shape, color: [u8; 3]) -> MeshId {
+        *self.ids.entry(shape.key(color)).or_insert_with(|| {
+            self.meshes.push(GpuMesh::new(device, &shape.mesh(color)));
+            MeshId(self.meshes.len() - 1)
+        })
+    }
+
+    pub fn get(&self, id: MeshId) -> &GpuMesh {
+        &self.meshes[id.0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.meshes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meshes.is_empty()
+    }
+}
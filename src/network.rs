@@ -0,0 +1,178 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryIter, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use crate::protocol::{Message, PlayerInfo, PROTOCOL_VERSION};
+use crate::sim::PlayerId;
+use crate::transport::{self, ConnectionStatus, Transport, TransportStats};
+
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+// how long the network thread waits for incoming messages before looking at outgoing ones again
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+pub enum NetworkCommand {
+    Send(Message),
+    Shutdown,
+}
+
+#[derive(Debug)]
+pub enum NetworkEvent {
+    Connecting,
+    Connected,
+    Welcome { id: PlayerId },
+    PlayerJoined { id: PlayerId, info: PlayerInfo },
+    PlayerLeft { id: PlayerId },
+    Snapshot { server_time: u64, players: Vec<(PlayerId, [f32; 2])> },
+    InputAck { sequence: u32, position: [f32; 2] },
+    Pong { timestamp: u64, server_time: u64 },
+    Disconnected(String),
+}
+
+// the game only ever talks to the connection through these channels, so nothing
+// on the render thread can block on the socket
+pub struct NetworkHandle {
+    commands: Sender<NetworkCommand>,
+    events: Receiver<NetworkEvent>,
+    // every session of this handle added up, kept current by the network thread
+    stats: Arc<Mutex<TransportStats>>,
+}
+impl NetworkHandle {
+    // never fails, the network thread keeps retrying in the background until it gets through
+    pub fn connect(host_addr: &str, info: PlayerInfo) -> Self {
+        let host_addr = host_addr.to_string();
+        Self::spawn(move || transport::connect(&host_addr), info)
+    }
+
+    // `connect` is called again for every retry, every session joins with the same info
+    pub fn spawn<F>(connect: F, info: PlayerInfo) -> Self
+    where
+        F: FnMut() -> Result<Box<dyn Transport>> + Send + 'static,
+    {
+        let (commands, command_receiver) = mpsc::channel();
+        let (event_sender, events) = mpsc::channel();
+        let stats = Arc::new(Mutex::new(TransportStats::default()));
+        let thread_stats = Arc::clone(&stats);
+        thread::spawn(move || run_network(connect, info, command_receiver, event_sender, thread_stats));
+        Self { commands, events, stats }
+    }
+
+    pub fn send(&self, message: Message) {
+        // if the network thread is gone the disconnect was already reported as an event
+        let _ = self.commands.send(NetworkCommand::Send(message));
+    }
+
+    pub fn events(&self) -> TryIter<'_, NetworkEvent> {
+        self.events.try_iter()
+    }
+
+    pub fn stats(&self) -> TransportStats {
+        *self.stats.lock().unwrap()
+    }
+}
+impl Drop for NetworkHandle {
+    fn drop(&mut self) {
+        let _ = self.commands.send(NetworkCommand::Shutdown);
+    }
+}
+
+enum SessionEnd {
+    Shutdown,
+    Lost(String),
+}
+
+fn run_network(mut connect: impl FnMut() -> Result<Box<dyn Transport>>, info: PlayerInfo, commands: Receiver<NetworkCommand>, events: Sender<NetworkEvent>, stats: Arc<Mutex<TransportStats>>) {
+    let mut retry_delay = INITIAL_RETRY_DELAY;
+    loop {
+        let previous_sessions = *stats.lock().unwrap();
+        let publish_stats = |session: TransportStats| *stats.lock().unwrap() = previous_sessions + session;
+        let _ = events.send(NetworkEvent::Connecting);
+        let end = match connect() {
+            Ok(transport) => {
+                let mut connected = false;
+                let end = run_session(transport, &info, &commands, &events, &mut connected, publish_stats);
+                if connected {
+                    retry_delay = INITIAL_RETRY_DELAY;
+                }
+                end
+            }
+            Err(err) => SessionEnd::Lost(err.to_string()),
+        };
+        let reason = match end {
+            SessionEnd::Shutdown => return,
+            SessionEnd::Lost(reason) => reason,
+        };
+        log::info!("disconnected: {reason}, retrying in {retry_delay:?}");
+        let _ = events.send(NetworkEvent::Disconnected(reason));
+
+        // offline until the next attempt, whatever the game sends meanwhile is dropped
+        let retry_at = Instant::now() + retry_delay;
+        loop {
+            match commands.recv_timeout(retry_at.saturating_duration_since(Instant::now())) {
+                Ok(NetworkCommand::Send(_)) => {}
+                Ok(NetworkCommand::Shutdown) | Err(RecvTimeoutError::Disconnected) => return,
+                Err(RecvTimeoutError::Timeout) => break,
+            }
+        }
+        retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+    }
+}
+
+fn run_session(mut transport: Box<dyn Transport>, info: &PlayerInfo, commands: &Receiver<NetworkCommand>, events: &Sender<NetworkEvent>, connected: &mut bool, publish_stats: impl Fn(TransportStats)) -> SessionEnd {
+    let handshake = transport.send(&Message::Hello { version: PROTOCOL_VERSION })
+        .and_then(|_| transport.send(&Message::Join { info: info.clone() }));
+    if let Err(err) = handshake {
+        return SessionEnd::Lost(err.to_string());
+    }
+    loop {
+        loop {
+            match commands.try_recv() {
+                Ok(NetworkCommand::Send(message)) => {
+                    if let Err(err) = transport.send(&message) {
+                        return SessionEnd::Lost(err.to_string());
+                    }
+                }
+                Ok(NetworkCommand::Shutdown) | Err(TryRecvError::Disconnected) => {
+                    let _ = transport.send(&Message::Disconnect);
+                    return SessionEnd::Shutdown;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        let received = transport.receive(POLL_INTERVAL);
+        publish_stats(transport.stats());
+        // udp only knows it is connected once the server answered
+        if !*connected && transport.status() == ConnectionStatus::Connected {
+            *connected = true;
+            let _ = events.send(NetworkEvent::Connected);
+        }
+        let message = match received {
+            Ok(Some(message)) => message,
+            Ok(None) => continue,
+            Err(err) if transport.status() == ConnectionStatus::Disconnected => return SessionEnd::Lost(err.to_string()),
+            Err(err) => {
+                log::warn!("dropping bad message from server: {err}");
+                continue;
+            }
+        };
+        let event = match message {
+            Message::Welcome { id } => NetworkEvent::Welcome { id },
+            Message::PlayerJoined { id, info } => NetworkEvent::PlayerJoined { id, info },
+            Message::PlayerLeft { id } => NetworkEvent::PlayerLeft { id },
+            Message::Snapshot { server_time, players } => NetworkEvent::Snapshot { server_time, players },
+            Message::InputAck { sequence, position } => NetworkEvent::InputAck { sequence, position },
+            Message::Pong { timestamp, server_time } => NetworkEvent::Pong { timestamp, server_time },
+            Message::Error { message } => return SessionEnd::Lost(format!("server error: {message}")),
+            Message::Disconnect => return SessionEnd::Lost("server closed the connection".to_string()),
+            message => {
+                log::warn!("unexpected message from server {message:?}");
+                continue;
+            }
+        };
+        if events.send(event).is_err() {
+            return SessionEnd::Shutdown;
+        }
+    }
+}
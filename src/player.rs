@@ -1,211 +1,185 @@
-use std::io::{Read, Write};
-use std::net::TcpStream;
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::sync::mpsc::TryIter;
+use std::time::{Duration, Instant};
 use anyhow::Result;
-use wgpu::{BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, Buffer, BufferBindingType, BufferUsages, Queue, ShaderStages};
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
-use crate::vertex::Vertex;
+use crate::clock::NetworkClock;
+use crate::network::{NetworkEvent, NetworkHandle};
+use crate::transport::{ConnectionStatus, Transport, TransportStats};
+use crate::protocol::{Message, PlayerInfo};
+use crate::sim::{self, Input, PlayerId, PlayerState};
 
 const BUFFER_SIZE: usize = 8;
-const MOVEMENT_SPEED: f32 = 0.05;
-pub enum PossibleMovements {
-    NoInput = 0,
-
-    Forward = 1,
-    Backwards = 2,
-    Left = 3,
-    Right = 4,
-}
+// in world units
+pub const PLAYER_RADIUS: f32 = 1.0 / 8.0;
 pub struct Player {
-    buffer: Vec<u8>,
-    pub stream: Arc<Mutex<TcpStream>>,
-
-    vertex_buffer: Buffer,
-    index_buffer: Buffer,
-    num_indices: u32,
-
-    position_buffer: Buffer,
-    position_bind_group: BindGroup,
-    position_bind_group_layout: BindGroupLayout,
-    position: [f32; 2],
+    // one input per tick, sent once there are BUFFER_SIZE of them
+    buffer: Vec<Input>,
+    next_sequence: u32,
+    // sent but not yet acknowledged by the server, replayed on top of every ack
+    pending_batches: VecDeque<(u32, Vec<Input>)>,
+    network: NetworkHandle,
+    connection_status: ConnectionStatus,
+    clock: NetworkClock,
+    info: PlayerInfo,
+    // handed out by the server in the handshake, only valid for the current session
+    id: Option<PlayerId>,
+
+
+    // predicted locally, corrected by every ack from the server
+    state: PlayerState,
 
     pub input: Input,
 }
 impl Player {
-    pub fn new(host_addr: &str, device: &wgpu::Device) -> Self {
-        let stream = Arc::new(Mutex::new(TcpStream::connect(host_addr).unwrap())); // this crashes the program if the host isnt hosting
-        let (vertices, indices) = Self::create_shape_optimized(40);
-        let vertex_buffer =  device.create_buffer_init(&BufferInitDescriptor{
-            label: Some("Vertex buffer"),
-            contents: bytemuck::cast_slice(vertices.as_slice()),
-            usage: BufferUsages::VERTEX,
-        });
-
-        let index_buffer = device.create_buffer_init(&BufferInitDescriptor{
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(indices.as_slice()),
-            usage: BufferUsages::INDEX,
-        });
-        let position = [0.0f32; 2];
-        let position_uniform = device.create_buffer_init(&BufferInitDescriptor{
-            label: Some("position uniform"),
-            contents: bytemuck::cast_slice(&position),
-            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-        });
-        let position_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor{
-            label: Some("position bind group layout"),
-            entries: &[BindGroupLayoutEntry{
-                binding: 0,
-                visibility: ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
-        let position_bind_group = device.create_bind_group(&BindGroupDescriptor{
-            label: Some("position bind group"),
-            layout: &position_bind_group_layout,
-            entries: &[BindGroupEntry{
-                binding: 0,
-                resource: position_uniform.as_entire_binding(),
-            }],
-        });
+    pub fn new(host_addr: &str, info: PlayerInfo) -> Self {
+        Self::with_network(NetworkHandle::connect(host_addr, info.clone()), info)
+    }
 
+    // joins over whatever `connect` returns instead of a socket, e.g. one end of a
+    // MemoryTransport pair. it is called again for every reconnect
+    pub fn with_connector<F>(connect: F, info: PlayerInfo) -> Self
+    where
+        F: FnMut() -> Result<Box<dyn Transport>> + Send + 'static,
+    {
+        Self::with_network(NetworkHandle::spawn(connect, info.clone()), info)
+    }
 
+    fn with_network(network: NetworkHandle, info: PlayerInfo) -> Self {
         Self{
             buffer: Vec::new(),
-            stream,
-            vertex_buffer,
-            index_buffer,
-            num_indices: indices.len() as u32,
+            next_sequence: 0,
+            pending_batches: VecDeque::new(),
+            network,
+            connection_status: ConnectionStatus::Disconnected,
+            clock: NetworkClock::new(),
+            info,
+            id: None,
 
-            position_buffer: position_uniform,
-            position_bind_group,
-            position_bind_group_layout,
-            position,
+            state: PlayerState::default(),
 
             input: Input::default(),
         }
     }
 
-    pub fn add_movement(&mut self, movement: PossibleMovements, queue: &Queue) -> Result<()> {
-        match movement {
-            PossibleMovements::NoInput => {
-                self.buffer.push(0);
-            }
-            PossibleMovements::Forward => {
-                self.buffer.push(1);
-                self.position[1] += MOVEMENT_SPEED;
-            }
-            PossibleMovements::Backwards => {
-                self.buffer.push(2);
-                self.position[1] -= MOVEMENT_SPEED;
-            }
-            PossibleMovements::Left => {
-                self.buffer.push(3);
-                self.position[0] -= MOVEMENT_SPEED;
-            }
-            PossibleMovements::Right => {
-                self.buffer.push(4);
-                self.position[0] += MOVEMENT_SPEED;
-            }
-        };
-        Self::rewrite_position_buffer(self, queue);
+    // called once per tick with the keys held right now
+    pub fn tick(&mut self) {
+        let input = self.input;
+        self.buffer.push(input);
+        self.state = self.state.step(input);
         if self.buffer.len() == BUFFER_SIZE {
-            {
-                Self::send_buffer(self)?;
-            }
-            self.buffer = Vec::new();
+            Self::send_buffer(self);
         }
-        Ok(())
-    }
-
-    pub fn send_buffer(&mut self) -> Result<()> {
-        println!("sending buffer");
-        let buffer = self.buffer.as_slice();
-        let stream = Arc::clone(&self.stream);
-        {
-            let mut stream = stream.lock().unwrap();
-            match stream.write(buffer){
-                Ok(_) => {}
-                Err(err) => { return Err(err.into()) }
-            };
+    }
+
+    pub fn send_buffer(&mut self) {
+        let inputs = std::mem::take(&mut self.buffer);
+        if self.connection_status != ConnectionStatus::Connected {
+            // offline mode, the player only moves locally
+            return;
         }
-        // make the read operation after the write op (and make them multithreaded)
-        Ok(())
-    }
-
-    pub fn get_buffers(&self) -> (&Buffer, &Buffer, u32, &BindGroup) {
-        (&self.vertex_buffer, &self.index_buffer, self.num_indices, &self.position_bind_group)
-    }
-    pub fn get_bind_group_layout(&self) -> &BindGroupLayout {
-        &self.position_bind_group_layout
-    }
-
-    pub fn get_players_position(stream: &Arc<Mutex<TcpStream>>) -> Vec<[f32; 2]> {
-        let mut buf: [u8; 4] = [0; 4];
-        let mut other_players_position: Vec<[f32; 2]> = vec![];
-        let mut stream = stream.lock().unwrap();
-        println!("peeking");
-        let len = stream.peek(&mut buf).unwrap();
-        println!("finished peeking");
-        // for _ in 0..len / 4 {
-        //     stream.read(&mut buf).unwrap();
-        //     let x = f32::from_be_bytes(buf);
-        //     stream.read(&mut buf).unwrap();
-        //     let y = f32::from_be_bytes(buf);
-        //     other_players_position.push([x, y]);
-        // }
-        other_players_position
-    }
-    fn rewrite_position_buffer(&mut self,  queue: &Queue){
-        queue.write_buffer(&self.position_buffer, 0, bytemuck::cast_slice(&self.position));
-    }
-    fn create_shape_optimized(num_vertices: u32) -> (Vec<Vertex>, Vec<u16>){
-        let mut vertices: Vec<Vertex> = Vec::new();
-
-        for i in 1..= num_vertices {
-            vertices.push(Vertex{
-                position: [
-                    f32::cos(f32::to_radians(i as f32*(90./(num_vertices as f32/4.)))) / 8.0,
-                    f32::sin(f32::to_radians(i as f32*(90./(num_vertices as f32/4.)))) / 8.0,
-                    0.0
-                ],
-                color: [1.0, 0.0, 1.0],
-            });
+        log::trace!("sending input batch {}", self.next_sequence);
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.network.send(Message::InputBatch { sequence, inputs: inputs.clone() });
+        self.pending_batches.push_back((sequence, inputs));
+    }
+
+    // rewinds to the server's position and replays every input it hasn't seen yet
+    pub fn reconcile(&mut self, acked_sequence: u32, server_position: [f32; 2]) {
+        while self.pending_batches.front().is_some_and(|(sequence, _)| *sequence <= acked_sequence) {
+            self.pending_batches.pop_front();
         }
-        let mut indices: Vec<u16> = Vec::new();
-        for i in 1..vertices.len() - 1 {
-            indices.push(0);
-            indices.push(i as u16);
-            indices.push((i + 1) as u16);
+        let unacked = self.pending_batches.iter()
+            .flat_map(|(_, inputs)| inputs)
+            .chain(&self.buffer);
+        self.state = sim::replay(PlayerState { position: server_position }, unacked);
+    }
+
+    // drops the current session, the old network thread shuts itself down.
+    // the server only learns about new info when joining, so changing it reconnects too
+    pub fn change_host(&mut self, host_addr: &str, info: PlayerInfo) {
+        self.network = NetworkHandle::connect(host_addr, info.clone());
+        self.info = info;
+        self.buffer.clear();
+        self.set_connection_status(ConnectionStatus::Disconnected);
+    }
+
+    // batches sent but not acked yet
+    pub fn unacked_len(&self) -> usize {
+        self.pending_batches.len()
+    }
+
+    pub fn connection_status(&self) -> ConnectionStatus {
+        self.connection_status
+    }
+
+    pub fn set_connection_status(&mut self, status: ConnectionStatus) {
+        if status != ConnectionStatus::Connected {
+            // a new session starts from scratch on the server, nothing sent so far will be acked
+            self.pending_batches.clear();
+            self.clock.reset();
+            self.id = None;
         }
-        (vertices, indices)
+        self.connection_status = status;
     }
-}
-pub struct Input{
-    pub forward: bool,
-    pub backward: bool,
-    pub left: bool,
-    pub right: bool,
-}
-impl Default for Input{
-    fn default() -> Self {
-        Input{
-            forward: false,
-            backward: false,
-            left: false,
-            right: false,
+
+    pub fn info(&self) -> &PlayerInfo {
+        &self.info
+    }
+
+    pub fn id(&self) -> Option<PlayerId> {
+        self.id
+    }
+
+    pub fn set_id(&mut self, id: PlayerId) {
+        self.id = Some(id);
+    }
+
+    // call every frame, pings the server once in a while to keep the clock in sync
+    pub fn ping(&mut self) {
+        if self.connection_status != ConnectionStatus::Connected {
+            return;
+        }
+        if let Some(ping) = self.clock.ping_due(Instant::now()) {
+            self.network.send(ping);
         }
     }
-}
-impl Input{
-    pub fn input(&self) -> bool {
-        self.right || self.backward || self.left || self.forward
+
+    pub fn receive_pong(&mut self, timestamp: u64, server_time: u64) {
+        self.clock.on_pong(timestamp, server_time, Instant::now());
+    }
+
+    // smoothed round trip time, None until the first pong
+    pub fn rtt(&self) -> Option<Duration> {
+        self.clock.rtt()
+    }
+
+    pub fn jitter(&self) -> Duration {
+        self.clock.jitter()
     }
 
-}
\ No newline at end of file
+    // estimated server clock in milliseconds, the same clock snapshots are stamped with
+    pub fn server_time(&self) -> Option<f64> {
+        self.clock.server_time(Instant::now())
+    }
+
+    // bytes and packets moved since the last change of host
+    pub fn network_stats(&self) -> TransportStats {
+        self.network.stats()
+    }
+
+    pub fn network_events(&self) -> TryIter<'_, NetworkEvent> {
+        self.network.events()
+    }
+
+    pub fn position(&self) -> [f32; 2] {
+        self.state.position
+    }
+}
+
+// someone else in the game, as far as this client knows
+pub struct RemotePlayer {
+    pub info: PlayerInfo,
+    // None until a snapshot with this player came in
+    pub position: Option<[f32; 2]>,
+}
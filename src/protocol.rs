@@ -0,0 +1,254 @@
+use std::io::{Read, Write};
+use anyhow::{bail, ensure, Result};
+use crate::sim::{Input, PlayerId};
+
+// every frame on the wire is a big endian u32 length followed by that many bytes:
+// a one byte message tag and the message body, all numbers are big endian
+pub const PROTOCOL_VERSION: u16 = 6;
+pub const MAX_FRAME_LEN: usize = 64 * 1024;
+const LEN_PREFIX_SIZE: usize = 4;
+
+const TAG_HELLO: u8 = 0;
+const TAG_INPUT_BATCH: u8 = 1;
+const TAG_SNAPSHOT: u8 = 2;
+const TAG_PING: u8 = 3;
+const TAG_PONG: u8 = 4;
+const TAG_DISCONNECT: u8 = 5;
+const TAG_ERROR: u8 = 6;
+const TAG_INPUT_ACK: u8 = 7;
+const TAG_JOIN: u8 = 8;
+const TAG_WELCOME: u8 = 9;
+const TAG_PLAYER_JOINED: u8 = 10;
+const TAG_PLAYER_LEFT: u8 = 11;
+
+// how a player wants to be shown to everyone else
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerInfo {
+    pub nickname: String,
+    pub color: [u8; 3],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    // the handshake is hello, then join, answered by a welcome with the client's own id
+    Hello { version: u16 },
+    Join { info: PlayerInfo },
+    Welcome { id: PlayerId },
+    // sent for everyone already in the game on join, and for everyone who joins later
+    PlayerJoined { id: PlayerId, info: PlayerInfo },
+    PlayerLeft { id: PlayerId },
+    // one input byte per tick, oldest first
+    InputBatch { sequence: u32, inputs: Vec<Input> },
+    // last input batch the server applied and where that left the player
+    InputAck { sequence: u32, position: [f32; 2] },
+    // server_time is in milliseconds since the server started
+    Snapshot { server_time: u64, players: Vec<(PlayerId, [f32; 2])> },
+    Ping { timestamp: u64 },
+    // echoes the ping's timestamp along with the server time it was answered at
+    Pong { timestamp: u64, server_time: u64 },
+    Disconnect,
+    Error { message: String },
+}
+
+impl Message {
+    // just the variant, for errors that shouldn't carry whatever the peer put inside
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Message::Hello { .. } => "hello",
+            Message::Join { .. } => "join",
+            Message::Welcome { .. } => "welcome",
+            Message::PlayerJoined { .. } => "player joined",
+            Message::PlayerLeft { .. } => "player left",
+            Message::InputBatch { .. } => "input batch",
+            Message::InputAck { .. } => "input ack",
+            Message::Snapshot { .. } => "snapshot",
+            Message::Ping { .. } => "ping",
+            Message::Pong { .. } => "pong",
+            Message::Disconnect => "disconnect",
+            Message::Error { .. } => "error",
+        }
+    }
+}
+
+// returns the whole frame, length prefix included. fails for messages the other end would
+// refuse as too big, which also covers every count or string too long for its u16 length
+pub fn encode(message: &Message) -> Result<Vec<u8>> {
+    let mut frame = vec![0u8; LEN_PREFIX_SIZE];
+    match message {
+        Message::Hello { version } => {
+            frame.push(TAG_HELLO);
+            frame.extend_from_slice(&version.to_be_bytes());
+        }
+        Message::Join { info } => {
+            frame.push(TAG_JOIN);
+            write_info(&mut frame, info);
+        }
+        Message::Welcome { id } => {
+            frame.push(TAG_WELCOME);
+            frame.extend_from_slice(&id.to_be_bytes());
+        }
+        Message::PlayerJoined { id, info } => {
+            frame.push(TAG_PLAYER_JOINED);
+            frame.extend_from_slice(&id.to_be_bytes());
+            write_info(&mut frame, info);
+        }
+        Message::PlayerLeft { id } => {
+            frame.push(TAG_PLAYER_LEFT);
+            frame.extend_from_slice(&id.to_be_bytes());
+        }
+        Message::InputBatch { sequence, inputs } => {
+            frame.push(TAG_INPUT_BATCH);
+            frame.extend_from_slice(&sequence.to_be_bytes());
+            frame.extend_from_slice(&(inputs.len() as u16).to_be_bytes());
+            frame.extend(inputs.iter().map(|input| input.to_byte()));
+        }
+        Message::InputAck { sequence, position: [x, y] } => {
+            frame.push(TAG_INPUT_ACK);
+            frame.extend_from_slice(&sequence.to_be_bytes());
+            frame.extend_from_slice(&x.to_be_bytes());
+            frame.extend_from_slice(&y.to_be_bytes());
+        }
+        Message::Snapshot { server_time, players } => {
+            frame.push(TAG_SNAPSHOT);
+            frame.extend_from_slice(&server_time.to_be_bytes());
+            frame.extend_from_slice(&(players.len() as u16).to_be_bytes());
+            for (id, [x, y]) in players {
+                frame.extend_from_slice(&id.to_be_bytes());
+                frame.extend_from_slice(&x.to_be_bytes());
+                frame.extend_from_slice(&y.to_be_bytes());
+            }
+        }
+        Message::Ping { timestamp } => {
+            frame.push(TAG_PING);
+            frame.extend_from_slice(&timestamp.to_be_bytes());
+        }
+        Message::Pong { timestamp, server_time } => {
+            frame.push(TAG_PONG);
+            frame.extend_from_slice(&timestamp.to_be_bytes());
+            frame.extend_from_slice(&server_time.to_be_bytes());
+        }
+        Message::Disconnect => frame.push(TAG_DISCONNECT),
+        Message::Error { message } => {
+            frame.push(TAG_ERROR);
+            write_string(&mut frame, message);
+        }
+    }
+    let len = frame.len() - LEN_PREFIX_SIZE;
+    ensure!(len <= MAX_FRAME_LEN, "{} of {len} bytes doesn't fit in a frame", message.kind());
+    frame[..LEN_PREFIX_SIZE].copy_from_slice(&(len as u32).to_be_bytes());
+    Ok(frame)
+}
+
+// u16 length followed by utf-8
+fn write_string(frame: &mut Vec<u8>, string: &str) {
+    frame.extend_from_slice(&(string.len() as u16).to_be_bytes());
+    frame.extend_from_slice(string.as_bytes());
+}
+
+fn write_info(frame: &mut Vec<u8>, info: &PlayerInfo) {
+    write_string(frame, &info.nickname);
+    frame.extend_from_slice(&info.color);
+}
+
+// decodes a frame body, i.e. everything after the length prefix
+pub fn decode(body: &[u8]) -> Result<Message> {
+    let mut reader = BodyReader { bytes: body };
+    let message = match reader.u8()? {
+        TAG_HELLO => Message::Hello { version: reader.u16()? },
+        TAG_JOIN => Message::Join { info: reader.info()? },
+        TAG_WELCOME => Message::Welcome { id: reader.u32()? },
+        TAG_PLAYER_JOINED => Message::PlayerJoined { id: reader.u32()?, info: reader.info()? },
+        TAG_PLAYER_LEFT => Message::PlayerLeft { id: reader.u32()? },
+        TAG_INPUT_BATCH => {
+            let sequence = reader.u32()?;
+            let len = reader.u16()? as usize;
+            let inputs = reader.take(len)?
+                .iter()
+                .map(|byte| Input::from_byte(*byte)
+                    .ok_or_else(|| anyhow::anyhow!("unknown input bits {byte:#010b}")))
+                .collect::<Result<_>>()?;
+            Message::InputBatch { sequence, inputs }
+        }
+        TAG_INPUT_ACK => Message::InputAck {
+            sequence: reader.u32()?,
+            position: [reader.f32()?, reader.f32()?],
+        },
+        TAG_SNAPSHOT => {
+            let server_time = reader.u64()?;
+            let len = reader.u16()? as usize;
+            let mut players = Vec::with_capacity(len);
+            for _ in 0..len {
+                players.push((reader.u32()?, [reader.f32()?, reader.f32()?]));
+            }
+            Message::Snapshot { server_time, players }
+        }
+        TAG_PING => Message::Ping { timestamp: reader.u64()? },
+        TAG_PONG => Message::Pong { timestamp: reader.u64()?, server_time: reader.u64()? },
+        TAG_DISCONNECT => Message::Disconnect,
+        TAG_ERROR => Message::Error { message: reader.string()? },
+        tag => bail!("unknown message tag {tag}"),
+    };
+    ensure!(reader.bytes.is_empty(), "{} trailing bytes after message", reader.bytes.len());
+    Ok(message)
+}
+
+// splits the first complete frame off the front of `bytes`, returns None until all of it has arrived
+pub fn decode_frame(bytes: &[u8]) -> Result<Option<(Message, usize)>> {
+    let Some(len) = bytes.get(..LEN_PREFIX_SIZE) else { return Ok(None) };
+    let len = u32::from_be_bytes(len.try_into()?) as usize;
+    ensure!(len <= MAX_FRAME_LEN, "frame of {len} bytes is too big");
+    let Some(body) = bytes.get(LEN_PREFIX_SIZE..LEN_PREFIX_SIZE + len) else { return Ok(None) };
+    Ok(Some((decode(body)?, LEN_PREFIX_SIZE + len)))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Message) -> Result<()> {
+    writer.write_all(&encode(message)?)?;
+    Ok(())
+}
+
+pub fn read_message<R: Read>(reader: &mut R) -> Result<Message> {
+    let mut len = [0u8; LEN_PREFIX_SIZE];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    ensure!(len <= MAX_FRAME_LEN, "frame of {len} bytes is too big");
+    let mut body = vec![0u8; len];
+    reader.read_exact(&mut body)?;
+    decode(&body)
+}
+
+struct BodyReader<'a> {
+    bytes: &'a [u8],
+}
+impl<'a> BodyReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        ensure!(self.bytes.len() >= len, "message is truncated");
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into()?))
+    }
+    fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+    fn string(&mut self) -> Result<String> {
+        let len = self.u16()? as usize;
+        Ok(String::from_utf8(self.take(len)?.to_vec())?)
+    }
+    fn info(&mut self) -> Result<PlayerInfo> {
+        Ok(PlayerInfo {
+            nickname: self.string()?,
+            color: self.take(3)?.try_into()?,
+        })
+    }
+}
@@ -0,0 +1,209 @@
+use std::path::Path;
+use anyhow::Result;
+use wgpu::{BindGroupLayout, BlendState, Buffer, BufferDescriptor, BufferUsages, Color, ColorWrites, CommandEncoder, FragmentState, PipelineLayoutDescriptor, Queue, RenderPipeline, ShaderModuleDescriptor, ShaderSource, TextureFormat, TextureView, VertexBufferLayout, VertexState};
+use crate::camera::{Camera, CameraBinding};
+use crate::instance::Instance;
+use crate::mesh::{MeshCache, MeshId, Shape};
+use crate::player::PLAYER_RADIUS;
+use crate::sprite::Sprite;
+use crate::texture::Texture;
+use crate::vertex::{TexturedVertex, Vertex};
+
+pub const CLEAR_COLOR: Color = Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 };
+// grows to the next power of two once more instances show up
+const INITIAL_INSTANCE_CAPACITY: usize = 16;
+// white, so each player's instance color shows through. used unless another one is given
+const PLAYER_SPRITE: &[u8] = include_bytes!("sprites/player.png");
+// meshes are multiplied by each instance's color, white leaves it as it is
+pub const WHITE: [u8; 3] = [255, 255, 255];
+
+// everything drawn with one shape and color
+pub struct ShapeBatch<'a> {
+    pub shape: Shape,
+    pub color: [u8; 3],
+    pub instances: &'a [Instance],
+}
+
+// what one frame shows, shapes are drawn over the players
+pub struct Frame<'a> {
+    pub players: &'a [Instance],
+    pub shapes: &'a [ShapeBatch<'a>],
+}
+
+// the world as drawn by sprite.wgsl and shader.wgsl, doesn't care whether it ends up in a window or a texture
+pub struct SceneRenderer {
+    sprite_pipeline: RenderPipeline,
+    // flat colored meshes, for debug shapes
+    shape_pipeline: RenderPipeline,
+    camera_binding: CameraBinding,
+    // the players and then every batch's instances, back to back
+    instance_buffer: Buffer,
+    player_sprite: Sprite,
+    player_count: u32,
+    meshes: MeshCache,
+    // one draw call per batch, with where its instances start in the instance buffer
+    draws: Vec<(MeshId, u32, u32)>,
+}
+impl SceneRenderer {
+    // `player_sprite` is a png to draw players with instead of the bundled one
+    pub fn new(device: &wgpu::Device, queue: &Queue, format: TextureFormat, camera: &Camera, player_sprite: Option<&Path>) -> Result<Self> {
+        let camera_binding = CameraBinding::new(device, camera);
+        let texture_layout = Texture::bind_group_layout(device);
+
+        let sprite_pipeline = create_pipeline(
+            device,
+            "Sprite Pipeline",
+            include_str!("sprite.wgsl"),
+            &[camera_binding.bind_group_layout(), &texture_layout],
+            &[TexturedVertex::desc(), Instance::desc()],
+            format,
+            BlendState::ALPHA_BLENDING,
+        );
+        let shape_pipeline = create_pipeline(
+            device,
+            "Render Pipeline",
+            include_str!("shader.wgsl"),
+            &[camera_binding.bind_group_layout()],
+            &[Vertex::desc(), Instance::desc()],
+            format,
+            BlendState::REPLACE,
+        );
+
+        let texture = match player_sprite {
+            Some(path) => Texture::load(device, queue, path)?,
+            None => Texture::from_png(device, queue, PLAYER_SPRITE, "player sprite")?,
+        };
+        let player_sprite = Sprite::new(device, &texture, &texture_layout, [2.0 * PLAYER_RADIUS; 2]);
+
+        Ok(Self {
+            sprite_pipeline,
+            shape_pipeline,
+            camera_binding,
+            instance_buffer: create_instance_buffer(device, INITIAL_INSTANCE_CAPACITY),
+            player_sprite,
+            player_count: 0,
+            meshes: MeshCache::default(),
+            draws: Vec::new(),
+        })
+    }
+
+    // uploads what the next `draw` shows, meshes are only created the first time a shape shows up
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &Queue, camera: &Camera, frame: &Frame) {
+        let total = frame.players.len() + frame.shapes.iter().map(|batch| batch.instances.len()).sum::<usize>();
+        let needed = (total * std::mem::size_of::<Instance>()) as wgpu::BufferAddress;
+        if needed > self.instance_buffer.size() {
+            self.instance_buffer = create_instance_buffer(device, total.next_power_of_two());
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(frame.players));
+        self.player_count = frame.players.len() as u32;
+        self.draws.clear();
+        let mut first = frame.players.len();
+        for batch in frame.shapes.iter().filter(|batch| !batch.instances.is_empty()) {
+            let mesh = self.meshes.get_or_create(device, batch.shape, batch.color);
+            let offset = (first * std::mem::size_of::<Instance>()) as wgpu::BufferAddress;
+            queue.write_buffer(&self.instance_buffer, offset, bytemuck::cast_slice(batch.instances));
+            self.draws.push((mesh, first as u32, batch.instances.len() as u32));
+            first += batch.instances.len();
+        }
+        self.camera_binding.update(queue, camera);
+    }
+
+    // clears `view` and draws the players and then every batch, one draw call each
+    pub fn draw(&self, encoder: &mut CommandEncoder, view: &TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(CLEAR_COLOR),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_bind_group(0, self.camera_binding.bind_group(), &[]);
+        if self.player_count > 0 {
+            render_pass.set_pipeline(&self.sprite_pipeline);
+            self.player_sprite.bind(&mut render_pass);
+            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            render_pass.draw_indexed(0..self.player_sprite.num_indices(), 0, 0..self.player_count);
+        }
+        render_pass.set_pipeline(&self.shape_pipeline);
+        for &(mesh, first, count) in &self.draws {
+            let mesh = self.meshes.get(mesh);
+            mesh.bind(&mut render_pass);
+            // offsetting the buffer instead of the instance range, gl can't start instancing past 0
+            let offset = first as wgpu::BufferAddress * std::mem::size_of::<Instance>() as wgpu::BufferAddress;
+            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(offset..));
+            render_pass.draw_indexed(0..mesh.num_indices(), 0, 0..count);
+        }
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Instance Buffer"),
+        size: (capacity * std::mem::size_of::<Instance>()) as wgpu::BufferAddress,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+// both pipelines only differ in their shader, bindings, vertices and blending
+fn create_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    shader: &str,
+    bind_group_layouts: &[&BindGroupLayout],
+    buffers: &[VertexBufferLayout],
+    format: TextureFormat,
+    blend: BlendState,
+) -> RenderPipeline {
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(label),
+        source: ShaderSource::Wgsl(shader.into()),
+    });
+
+    let render_pipeline_layout =
+        device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(&render_pipeline_layout),
+        vertex: VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers,
+        },
+        fragment: Some(FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(blend),
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
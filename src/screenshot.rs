@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender, TryIter};
+use std::sync::{mpsc, Arc};
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{bail, Context, Result};
+use image::RgbaImage;
+use wgpu::{Buffer, BufferDescriptor, BufferUsages, CommandEncoder, Extent3d, ImageCopyBuffer, ImageDataLayout, MapMode, Queue, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages};
+use crate::camera::Camera;
+use crate::graphics::{self, GraphicsConfig};
+use winit::keyboard::KeyCode;
+use crate::scene::{Frame, SceneRenderer};
+
+// srgb like the window surface, so offscreen renders match what's on screen
+pub const OFFSCREEN_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+pub const CAPTURE_KEY: KeyCode = KeyCode::F12;
+// relative to wherever the game was started
+const SCREENSHOT_DIR: &str = "screenshots";
+
+// a device without a window, for screenshots and golden image tests
+pub async fn headless_device(config: &GraphicsConfig) -> Result<(wgpu::Device, Queue)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: config.backends,
+        dx12_shader_compiler: Default::default(),
+    });
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: config.power_preference,
+            compatible_surface: None,
+            force_fallback_adapter: config.force_fallback_adapter,
+        })
+        .await
+        .ok_or_else(|| config.no_adapter_error())?;
+    let adapter_info = adapter.get_info();
+    log::info!("rendering offscreen with {} on {:?}", adapter_info.name, adapter_info.backend);
+    graphics::request_device(&adapter).await
+}
+
+// renders the world into a texture of its own and reads it back, nothing is shown
+pub fn render_offscreen(device: &wgpu::Device, queue: &Queue, camera: &Camera, frame: &Frame, player_sprite: Option<&Path>, width: u32, height: u32) -> Result<RgbaImage> {
+    let texture = device.create_texture(&TextureDescriptor {
+        label: Some("offscreen target"),
+        size: Extent3d { width, height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: OFFSCREEN_FORMAT,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let mut scene = SceneRenderer::new(device, queue, OFFSCREEN_FORMAT, camera, player_sprite)?;
+    scene.prepare(device, queue, camera, frame);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("offscreen encoder"),
+    });
+    scene.draw(&mut encoder, &view);
+    let readback = Readback::new(device, &mut encoder, &texture)?;
+    queue.submit(std::iter::once(encoder.finish()));
+    readback.wait(device)
+}
+
+pub fn save_png(image: &RgbaImage, path: &Path) -> Result<()> {
+    image.save_with_format(path, image::ImageFormat::Png)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+// a copy of a texture on its way back from the gpu, rows are padded to what wgpu wants
+pub struct Readback {
+    buffer: Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    // surfaces are often bgra, images are always rgba
+    bgra: bool,
+}
+impl Readback {
+    // records the copy into `encoder`, which has to be submitted before mapping
+    pub fn new(device: &wgpu::Device, encoder: &mut CommandEncoder, texture: &Texture) -> Result<Self> {
+        let bgra = match texture.format() {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            format => bail!("can't read back {format:?} textures"),
+        };
+        let (width, height) = (texture.width(), texture.height());
+        let padded_bytes_per_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("readback buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        Ok(Self { buffer, width, height, padded_bytes_per_row, bgra })
+    }
+
+    // `on_mapped` runs from a later `Device::poll` once the copy is done, the caller keeps rendering meanwhile
+    pub fn map(self, on_mapped: impl FnOnce(Result<RgbaImage>) + Send + 'static) {
+        let readback = Arc::new(self);
+        let mapped = readback.clone();
+        readback.buffer.slice(..).map_async(MapMode::Read, move |result| {
+            on_mapped(result.context("failed to map the readback buffer").map(|()| mapped.to_image()));
+        });
+    }
+
+    // blocks until the gpu is done
+    pub fn wait(self, device: &wgpu::Device) -> Result<RgbaImage> {
+        let (sender, receiver) = mpsc::channel();
+        self.map(move |image| {
+            let _ = sender.send(image);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv().context("the readback was dropped")?
+    }
+
+    fn to_image(&self) -> RgbaImage {
+        let row_bytes = (self.width * 4) as usize;
+        let mut pixels = Vec::with_capacity(row_bytes * self.height as usize);
+        {
+            let data = self.buffer.slice(..).get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..row_bytes]);
+            }
+        }
+        self.buffer.unmap();
+        if self.bgra {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        RgbaImage::from_raw(self.width, self.height, pixels).expect("the buffer holds exactly one image")
+    }
+}
+
+// frames captured with F12, the copy and the png encoding both happen off the event loop
+// and finished saves come back through `finished` so the hud can say where they went
+pub struct ScreenshotQueue {
+    requested: bool,
+    sender: Sender<Result<PathBuf>>,
+    receiver: Receiver<Result<PathBuf>>,
+}
+impl Default for ScreenshotQueue {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { requested: false, sender, receiver }
+    }
+}
+impl ScreenshotQueue {
+    // the next rendered frame gets saved
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    // copies `texture` in `encoder` if a screenshot was requested, after everything else was drawn into it
+    pub fn capture(&mut self, device: &wgpu::Device, encoder: &mut CommandEncoder, texture: &Texture) -> Option<Readback> {
+        if !std::mem::take(&mut self.requested) {
+            return None;
+        }
+        match Readback::new(device, encoder, texture) {
+            Ok(readback) => Some(readback),
+            Err(err) => {
+                let _ = self.sender.send(Err(err));
+                None
+            }
+        }
+    }
+
+    // once the encoder passed to `capture` was submitted, the png is written on a thread of its own
+    pub fn save(&self, readback: Readback) {
+        let path = Path::new(SCREENSHOT_DIR).join(file_name(SystemTime::now()));
+        let sender = self.sender.clone();
+        readback.map(move |image| {
+            std::thread::spawn(move || {
+                let saved = image.and_then(|image| {
+                    std::fs::create_dir_all(SCREENSHOT_DIR).with_context(|| format!("failed to create {SCREENSHOT_DIR}"))?;
+                    save_png(&image, &path)
+                });
+                let _ = sender.send(saved.map(|()| path));
+            });
+        });
+    }
+
+    pub fn finished(&self) -> TryIter<'_, Result<PathBuf>> {
+        self.receiver.try_iter()
+    }
+}
+
+// like screenshot-2023-07-21_18-04-09.123.png, in utc so they sort by time
+pub fn file_name(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    let (hour, minute, second) = (seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+    let millis = since_epoch.subsec_millis();
+    format!("screenshot-{year:04}-{month:02}-{day:02}_{hour:02}-{minute:02}-{second:02}.{millis:03}.png")
+}
+
+// days since 1970-01-01 to a proleptic gregorian date, from howard hinnant's date algorithms
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, bail, Result};
+use crate::protocol::{Message, PlayerInfo, PROTOCOL_VERSION};
+use crate::sim::{self, PlayerId, PlayerState, WorldState};
+use crate::transport::{ConnectionStatus, TcpTransport, Transport, UdpListener};
+
+const BROADCAST_INTERVAL: Duration = Duration::from_millis(50);
+// how long the server sleeps between looking for new messages
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+const MAX_NICKNAME_CHARS: usize = 16;
+// clients that connect and then never say hello and join don't get to keep a slot
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+// how far ahead of the clock a client may get with its inputs, enough for a few
+// batches that were held up on the way. anything beyond is dropped
+const MAX_INPUT_BACKLOG: Duration = Duration::from_millis(500);
+
+// what the server waits for next from a client
+#[derive(Copy, Clone, PartialEq)]
+enum Handshake {
+    Hello,
+    Join,
+    Done,
+}
+
+struct Client {
+    transport: Box<dyn Transport>,
+    handshake: Handshake,
+    // the handshake has to be done by then
+    handshake_deadline: Instant,
+    info: PlayerInfo,
+    // whether everyone else was told this player joined
+    announced: bool,
+    // newest input batch applied to position, acked on every broadcast
+    last_sequence: Option<u32>,
+    // time the client may still fill with inputs, refilled as the server clock moves on
+    input_budget: Duration,
+    // server time in ms when the budget was last refilled
+    budget_refilled: u64,
+}
+
+// owns every player's position, clients reach it through any transport handed to the connector
+pub struct Server {
+    connector: Sender<Box<dyn Transport>>,
+    new_clients: Receiver<Box<dyn Transport>>,
+    tcp_addr: Option<SocketAddr>,
+    // how long a new client has to finish the handshake
+    pub handshake_timeout: Duration,
+}
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Server {
+    pub fn new() -> Self {
+        let (connector, new_clients) = mpsc::channel();
+        Self {
+            connector,
+            new_clients,
+            tcp_addr: None,
+            handshake_timeout: HANDSHAKE_TIMEOUT,
+        }
+    }
+
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let mut server = Self::new();
+        server.listen_tcp(addr)?;
+        Ok(server)
+    }
+
+    // address of the first tcp listener
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.tcp_addr.ok_or_else(|| anyhow!("server is not listening on tcp"))
+    }
+
+    pub fn listen_tcp<A: ToSocketAddrs>(&mut self, addr: A) -> Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        self.tcp_addr.get_or_insert(addr);
+        let connector = self.connector();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let transport = match stream.map_err(anyhow::Error::from).and_then(TcpTransport::new) {
+                    Ok(transport) => transport,
+                    Err(err) => {
+                        log::warn!("failed to accept tcp client: {err}");
+                        continue;
+                    }
+                };
+                if connector.send(Box::new(transport)).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(addr)
+    }
+
+    pub fn listen_udp<A: ToSocketAddrs>(&mut self, addr: A) -> Result<SocketAddr> {
+        let listener = UdpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let connector = self.connector();
+        thread::spawn(move || {
+            let result = listener.run(|peer| {
+                log::info!("udp client at {}", peer.addr());
+                connector.send(Box::new(peer)).is_ok()
+            });
+            if let Err(err) = result {
+                log::warn!("udp listener stopped: {err}");
+            }
+        });
+        Ok(addr)
+    }
+
+    // every transport sent here joins the game, e.g. one end of a MemoryTransport pair
+    pub fn connector(&self) -> Sender<Box<dyn Transport>> {
+        self.connector.clone()
+    }
+
+    // blocks forever
+    pub fn run(self) -> Result<()> {
+        let started = Instant::now();
+        let mut clients: BTreeMap<PlayerId, Client> = BTreeMap::new();
+        let mut world = WorldState::default();
+        let mut next_id: PlayerId = 0;
+        let mut next_broadcast = started + BROADCAST_INTERVAL;
+        loop {
+            for transport in self.new_clients.try_iter() {
+                log::info!("player {next_id} connected");
+                clients.insert(next_id, Client {
+                    transport,
+                    handshake: Handshake::Hello,
+                    handshake_deadline: Instant::now() + self.handshake_timeout,
+                    info: PlayerInfo::default(),
+                    announced: false,
+                    last_sequence: None,
+                    input_budget: MAX_INPUT_BACKLOG,
+                    budget_refilled: 0,
+                });
+                next_id += 1;
+            }
+            let server_time = started.elapsed().as_millis() as u64;
+            clients.retain(|id, client| match receive_all(*id, client, &mut world, server_time) {
+                Ok(true) => true,
+                Ok(false) => {
+                    log::info!("player {id} disconnected");
+                    false
+                }
+                Err(err) => {
+                    log::info!("dropping player {id}: {err}");
+                    let _ = client.transport.send(&Message::Error { message: err.to_string() });
+                    false
+                }
+            });
+            let mut left = Vec::new();
+            world.players.retain(|id, _| {
+                let connected = clients.contains_key(id);
+                if !connected {
+                    left.push(*id);
+                }
+                connected
+            });
+            announce(&mut clients, &left);
+            if Instant::now() >= next_broadcast {
+                broadcast(&mut clients, &world, server_time);
+                next_broadcast += BROADCAST_INTERVAL;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    pub fn spawn(self) -> thread::JoinHandle<Result<()>> {
+        thread::spawn(move || self.run())
+    }
+}
+
+// handles everything the client sent since the last call, false once it is gone
+fn receive_all(id: PlayerId, client: &mut Client, world: &mut WorldState, server_time: u64) -> Result<bool> {
+    if client.handshake != Handshake::Done && Instant::now() >= client.handshake_deadline {
+        bail!("took too long to say hello and join");
+    }
+    loop {
+        let message = match client.transport.receive(Duration::ZERO) {
+            Ok(Some(message)) => message,
+            Ok(None) => return Ok(client.transport.status() != ConnectionStatus::Disconnected),
+            Err(err) if client.transport.status() == ConnectionStatus::Disconnected => {
+                log::info!("connection lost: {err}");
+                return Ok(false);
+            }
+            Err(err) => {
+                log::warn!("dropping bad message: {err}");
+                continue;
+            }
+        };
+        // the handshake has to be done before the client shows up in snapshots
+        match (client.handshake, message) {
+            (_, Message::Disconnect) => return Ok(false),
+            (Handshake::Hello, Message::Hello { version }) if version == PROTOCOL_VERSION => {
+                client.handshake = Handshake::Join;
+            }
+            (Handshake::Hello, Message::Hello { version }) => bail!("unsupported protocol version {version}, expected {PROTOCOL_VERSION}"),
+            (Handshake::Hello, message) => bail!("expected a hello, got a {}", message.kind()),
+            (Handshake::Join, Message::Join { info }) => {
+                client.info = PlayerInfo { nickname: sanitize_nickname(&info.nickname, id), color: info.color };
+                log::info!("player {id} joined as {:?}", client.info.nickname);
+                client.handshake = Handshake::Done;
+                client.budget_refilled = server_time;
+                client.transport.send(&Message::Welcome { id })?;
+                world.players.insert(id, PlayerState::default());
+            }
+            (Handshake::Join, message) => bail!("expected a join, got a {}", message.kind()),
+            (Handshake::Done, message) => handle_message(id, client, world, message, server_time)?,
+        }
+    }
+}
+
+// everything a client may send once it is in the game
+fn handle_message(id: PlayerId, client: &mut Client, world: &mut WorldState, message: Message, server_time: u64) -> Result<()> {
+    match message {
+        Message::InputBatch { sequence, inputs } => {
+            if client.last_sequence.is_some_and(|last| sequence <= last) {
+                return Ok(());
+            }
+            // clients send their inputs at their own pace, but no faster than ticks pass
+            // on the server, so a client can't move faster by sending more of them
+            let elapsed = Duration::from_millis(server_time.saturating_sub(client.budget_refilled));
+            client.budget_refilled = server_time;
+            client.input_budget = (client.input_budget + elapsed).min(MAX_INPUT_BACKLOG);
+            let allowed = (client.input_budget.as_nanos() / sim::TICK_DURATION.as_nanos()) as usize;
+            if inputs.len() > allowed {
+                log::debug!("player {id} sent {} inputs, only {allowed} fit", inputs.len());
+            }
+            let accepted = inputs.len().min(allowed);
+            client.input_budget -= sim::TICK_DURATION * accepted as u32;
+            if let Some(player) = world.players.get_mut(&id) {
+                *player = sim::replay(*player, &inputs[..accepted]);
+            }
+            client.last_sequence = Some(sequence);
+        }
+        Message::Ping { timestamp } => client.transport.send(&Message::Pong { timestamp, server_time })?,
+        message => bail!("unexpected {} message", message.kind()),
+    }
+    Ok(())
+}
+
+fn sanitize_nickname(nickname: &str, id: PlayerId) -> String {
+    let nickname: String = nickname.trim().chars().filter(|c| !c.is_control()).take(MAX_NICKNAME_CHARS).collect();
+    if nickname.is_empty() {
+        format!("player {id}")
+    } else {
+        nickname
+    }
+}
+
+// introduces players who finished the handshake and says goodbye for the ones in `left`.
+// a newcomer gets everyone already announced, then is announced to them
+fn announce(clients: &mut BTreeMap<PlayerId, Client>, left: &[PlayerId]) {
+    let mut messages: Vec<(PlayerId, Message)> = Vec::new();
+    let newcomers: Vec<PlayerId> = clients.iter()
+        .filter(|(_, client)| client.handshake == Handshake::Done && !client.announced)
+        .map(|(id, _)| *id)
+        .collect();
+    for id in newcomers {
+        for (other_id, other) in clients.iter().filter(|(_, client)| client.announced) {
+            messages.push((id, Message::PlayerJoined { id: *other_id, info: other.info.clone() }));
+            messages.push((*other_id, Message::PlayerJoined { id, info: clients[&id].info.clone() }));
+        }
+        if let Some(client) = clients.get_mut(&id) {
+            client.announced = true;
+        }
+    }
+    for id in left {
+        for (other_id, _) in clients.iter().filter(|(_, client)| client.announced) {
+            messages.push((*other_id, Message::PlayerLeft { id: *id }));
+        }
+    }
+    for (id, message) in messages {
+        let client = clients.get_mut(&id).expect("messages only go to connected clients");
+        // a client that is gone gets dropped the next time it is read from
+        if let Err(err) = client.transport.send(&message) {
+            log::info!("failed to send to player {id}: {err}");
+        }
+    }
+}
+
+fn broadcast(clients: &mut BTreeMap<PlayerId, Client>, world: &WorldState, server_time: u64) {
+    let players = world.players.iter()
+        .map(|(id, player)| (*id, player.position))
+        .collect();
+    let snapshot = Message::Snapshot { server_time, players };
+    clients.retain(|id, client| {
+        let Some(player) = world.players.get(id) else { return true };
+        let mut result = Ok(());
+        if let Some(sequence) = client.last_sequence {
+            result = client.transport.send(&Message::InputAck { sequence, position: player.position });
+        }
+        match result.and_then(|_| client.transport.send(&snapshot)) {
+            Ok(_) => true,
+            Err(err) => {
+                log::info!("dropping player {id}: {err}");
+                false
+            }
+        }
+    });
+}
@@ -2,24 +2,33 @@ struct VertexInput{
     @location(0) position: vec3<f32>,
     @location(1) color: vec3<f32>
 };
+struct InstanceInput{
+    @location(2) position: vec2<f32>,
+    @location(3) color: vec3<f32>,
+};
+struct CameraUniform{
+    view_projection: mat4x4<f32>,
+};
 struct VertexOutput{
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec3<f32>,
 };
 
 @group(0) @binding(0)
-var<uniform> position: vec2<f32>;
+var<uniform> camera: CameraUniform;
 
 @vertex
 fn vs_main(
     model: VertexInput,
+    instance: InstanceInput,
 ) -> VertexOutput{
     var out: VertexOutput;
-    out.clip_position = vec4(model.position.x + position.x, model.position.y + position.y, model.position.z, 1.0);
-    out.color = model.color;
+    let world_position = vec4(model.position.xy + instance.position, model.position.z, 1.0);
+    out.clip_position = camera.view_projection * world_position;
+    out.color = model.color * instance.color;
     return out;
 }
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32>{
     return vec4(in.color, 1.0);
-}
\ No newline at end of file
+}
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// the game rules, shared by client prediction and the server. nothing in here touches
+// the gpu, the network or the clock, so the same inputs always give the same state
+
+pub const TICK_RATE: u32 = 60;
+pub const TICK_DURATION: Duration = Duration::from_nanos(1_000_000_000 / TICK_RATE as u64);
+// distance covered in one tick
+pub const MOVEMENT_SPEED: f32 = 0.05;
+
+pub type PlayerId = u32;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PlayerState {
+    pub position: [f32; 2],
+}
+impl PlayerState {
+    pub fn step(&self, input: Input) -> PlayerState {
+        let direction = input.direction();
+        PlayerState {
+            position: [
+                self.position[0] + direction[0] * MOVEMENT_SPEED,
+                self.position[1] + direction[1] * MOVEMENT_SPEED,
+            ],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WorldState {
+    pub players: BTreeMap<PlayerId, PlayerState>,
+}
+
+// one tick per input, oldest first. the server applies every batch it accepts with this
+// and the client replays whatever the server hasn't acked yet on top of each ack
+pub fn replay<'a>(state: PlayerState, inputs: impl IntoIterator<Item = &'a Input>) -> PlayerState {
+    inputs.into_iter().fold(state, |state, input| state.step(*input))
+}
+
+// the keys held during one tick, on the wire as a single byte of these bits.
+// the upper bits are kept for buttons that don't exist yet
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Input{
+    pub forward: bool,
+    pub backward: bool,
+    pub left: bool,
+    pub right: bool,
+}
+impl Input{
+    pub const FORWARD: u8 = 1 << 0;
+    pub const BACKWARD: u8 = 1 << 1;
+    pub const LEFT: u8 = 1 << 2;
+    pub const RIGHT: u8 = 1 << 3;
+    const ALL: u8 = Self::FORWARD | Self::BACKWARD | Self::LEFT | Self::RIGHT;
+
+    pub fn input(&self) -> bool {
+        self.right || self.backward || self.left || self.forward
+    }
+
+    pub fn to_byte(self) -> u8 {
+        let mut byte = 0;
+        for (held, bit) in [(self.forward, Self::FORWARD), (self.backward, Self::BACKWARD), (self.left, Self::LEFT), (self.right, Self::RIGHT)] {
+            if held {
+                byte |= bit;
+            }
+        }
+        byte
+    }
+
+    // None if a bit nobody knows about yet is set
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        if byte & !Self::ALL != 0 {
+            return None;
+        }
+        Some(Self {
+            forward: byte & Self::FORWARD != 0,
+            backward: byte & Self::BACKWARD != 0,
+            left: byte & Self::LEFT != 0,
+            right: byte & Self::RIGHT != 0,
+        })
+    }
+
+    // unit length or zero, so diagonals aren't faster than moving straight
+    pub fn direction(&self) -> [f32; 2] {
+        let axis = |positive: bool, negative: bool| positive as i8 as f32 - negative as i8 as f32;
+        let x = axis(self.right, self.left);
+        let y = axis(self.forward, self.backward);
+        if x == 0.0 && y == 0.0 {
+            return [0.0; 2];
+        }
+        let len = (x * x + y * y).sqrt();
+        [x / len, y / len]
+    }
+}
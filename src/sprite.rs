@@ -0,0 +1,55 @@
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{BindGroup, BindGroupLayout, Buffer, BufferUsages, RenderPass};
+use crate::texture::Texture;
+use crate::vertex::TexturedVertex;
+
+const QUAD_INDICES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+// a texture on a quad centered on the origin, drawn once per instance by sprite.wgsl
+pub struct Sprite {
+    bind_group: BindGroup,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+}
+impl Sprite {
+    // `size` in world units, `layout` from `Texture::bind_group_layout`
+    pub fn new(device: &wgpu::Device, texture: &Texture, layout: &BindGroupLayout, size: [f32; 2]) -> Self {
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor{
+            label: Some("Sprite Vertex Buffer"),
+            contents: bytemuck::cast_slice(&quad(size)),
+            usage: BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&BufferInitDescriptor{
+            label: Some("Sprite Index Buffer"),
+            contents: bytemuck::cast_slice(&QUAD_INDICES),
+            usage: BufferUsages::INDEX,
+        });
+        Self {
+            bind_group: texture.bind_group(device, layout),
+            vertex_buffer,
+            index_buffer,
+        }
+    }
+
+    // the texture goes in group 1 and the quad in slot 0, instances are up to the caller
+    pub fn bind<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+    }
+
+    pub fn num_indices(&self) -> u32 {
+        QUAD_INDICES.len() as u32
+    }
+}
+
+// counter clockwise from the bottom left, world y points up while texture v points down
+pub fn quad([width, height]: [f32; 2]) -> [TexturedVertex; 4] {
+    let (x, y) = (width / 2.0, height / 2.0);
+    [
+        TexturedVertex { position: [-x, -y, 0.0], uv: [0.0, 1.0] },
+        TexturedVertex { position: [x, -y, 0.0], uv: [1.0, 1.0] },
+        TexturedVertex { position: [x, y, 0.0], uv: [1.0, 0.0] },
+        TexturedVertex { position: [-x, y, 0.0], uv: [0.0, 0.0] },
+    ]
+}
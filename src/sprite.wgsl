@@ -0,0 +1,42 @@
+struct VertexInput{
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+};
+struct InstanceInput{
+    @location(2) position: vec2<f32>,
+    @location(3) color: vec3<f32>,
+};
+struct CameraUniform{
+    view_projection: mat4x4<f32>,
+};
+struct VertexOutput{
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec3<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+@group(1) @binding(0)
+var sprite_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var sprite_sampler: sampler;
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput{
+    var out: VertexOutput;
+    let world_position = vec4(model.position.xy + instance.position, model.position.z, 1.0);
+    out.clip_position = camera.view_projection * world_position;
+    out.uv = model.uv;
+    out.color = instance.color;
+    return out;
+}
+// the sprite is tinted by its instance, white parts take the instance's color
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32>{
+    let texel = textureSample(sprite_texture, sprite_sampler, in.uv);
+    return vec4(texel.rgb * in.color, texel.a);
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use wgpu::{BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Extent3d, ImageCopyTexture, ImageDataLayout, Queue, SamplerBindingType, ShaderStages, TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureViewDimension};
+
+// textures hold srgb colors like the pngs they come from, sampling gives linear ones
+const FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+// an image on the gpu with the sampler to read it with
+pub struct Texture {
+    view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+}
+impl Texture {
+    pub fn from_image(device: &wgpu::Device, queue: &Queue, image: &RgbaImage, label: &str) -> Self {
+        let (width, height) = image.dimensions();
+        let size = Extent3d { width, height, depth_or_array_layers: 1 };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: FORMAT,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            image,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        Self { view, sampler }
+    }
+
+    pub fn from_png(device: &wgpu::Device, queue: &Queue, bytes: &[u8], label: &str) -> Result<Self> {
+        let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)
+            .with_context(|| format!("{label} isn't a valid png"))?;
+        Ok(Self::from_image(device, queue, &image.into_rgba8(), label))
+    }
+
+    pub fn load(device: &wgpu::Device, queue: &Queue, path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_png(device, queue, &bytes, &path.display().to_string())
+    }
+
+    // the texture at binding 0 and its sampler at binding 1, for fragment shaders
+    pub fn bind_group_layout(device: &wgpu::Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("texture bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    pub fn bind_group(&self, device: &wgpu::Device, layout: &BindGroupLayout) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("texture bind group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&self.view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+}
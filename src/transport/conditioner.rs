@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::protocol::Message;
+use crate::transport::{ConnectionStatus, Transport, TransportStats};
+
+// a reordered item is held back this much longer than everything sent around it
+const REORDER_DELAY: Duration = Duration::from_millis(50);
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// applied to each direction separately, so the round trip gets twice the latency.
+// probabilities go from 0 to 1
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct NetworkConditions {
+    pub latency: Duration,
+    pub jitter: Duration,
+    pub loss: f32,
+    pub duplication: f32,
+    pub reordering: f32,
+}
+
+// holds items back until the simulated network would have delivered them
+pub struct Conditioner<T> {
+    conditions: NetworkConditions,
+    rng: StdRng,
+    // keyed by delivery time, the counter keeps items due at the same instant in order
+    queue: BTreeMap<(Instant, u64), T>,
+    next_id: u64,
+}
+impl<T: Clone> Conditioner<T> {
+    pub fn new(conditions: NetworkConditions) -> Self {
+        Self::with_rng(conditions, StdRng::from_entropy())
+    }
+
+    // same seed, same losses and delays, for reproducible tests
+    pub fn with_seed(conditions: NetworkConditions, seed: u64) -> Self {
+        Self::with_rng(conditions, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(conditions: NetworkConditions, rng: StdRng) -> Self {
+        Self {
+            conditions,
+            rng,
+            queue: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn conditions(&self) -> NetworkConditions {
+        self.conditions
+    }
+
+    pub fn push(&mut self, item: T, now: Instant) {
+        if self.rng.gen::<f32>() < self.conditions.loss {
+            return;
+        }
+        if self.rng.gen::<f32>() < self.conditions.duplication {
+            self.schedule(item.clone(), now);
+        }
+        self.schedule(item, now);
+    }
+
+    pub fn pop_ready(&mut self, now: Instant) -> Option<T> {
+        let entry = self.queue.first_entry()?;
+        if entry.key().0 > now {
+            return None;
+        }
+        Some(entry.remove())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    fn schedule(&mut self, item: T, now: Instant) {
+        let jitter = self.conditions.jitter.as_secs_f64();
+        let offset = if jitter > 0.0 { self.rng.gen_range(-jitter..=jitter) } else { 0.0 };
+        let mut delay = Duration::from_secs_f64((self.conditions.latency.as_secs_f64() + offset).max(0.0));
+        if self.rng.gen::<f32>() < self.conditions.reordering {
+            delay += REORDER_DELAY;
+        }
+        self.queue.insert((now + delay, self.next_id), item);
+        self.next_id += 1;
+    }
+}
+
+// puts simulated network conditions between the game and any transport. over a
+// stream transport this produces loss and reordering tcp itself never would, which
+// is exactly what prediction and interpolation have to cope with over udp
+pub struct ConditionedTransport<T> {
+    inner: T,
+    outgoing: Conditioner<Message>,
+    incoming: Conditioner<Message>,
+}
+impl<T: Transport> ConditionedTransport<T> {
+    pub fn new(inner: T, conditions: NetworkConditions) -> Self {
+        Self {
+            inner,
+            outgoing: Conditioner::new(conditions),
+            incoming: Conditioner::new(conditions),
+        }
+    }
+
+    pub fn with_seed(inner: T, conditions: NetworkConditions, seed: u64) -> Self {
+        Self {
+            inner,
+            outgoing: Conditioner::with_seed(conditions, seed),
+            incoming: Conditioner::with_seed(conditions, seed.wrapping_add(1)),
+        }
+    }
+
+    // moves everything that is due in either direction
+    fn pump(&mut self, now: Instant) -> Result<()> {
+        while let Some(message) = self.outgoing.pop_ready(now) {
+            self.inner.send(&message)?;
+        }
+        while let Some(message) = self.inner.receive(Duration::ZERO)? {
+            self.incoming.push(message, now);
+        }
+        Ok(())
+    }
+}
+impl<T: Transport> Transport for ConditionedTransport<T> {
+    fn send(&mut self, message: &Message) -> Result<()> {
+        let now = Instant::now();
+        self.outgoing.push(message.clone(), now);
+        self.pump(now)
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Message>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let now = Instant::now();
+            self.pump(now)?;
+            if let Some(message) = self.incoming.pop_ready(now) {
+                return Ok(Some(message));
+            }
+            if now >= deadline {
+                return Ok(None);
+            }
+            std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+        }
+    }
+
+    fn status(&self) -> ConnectionStatus {
+        self.inner.status()
+    }
+
+    // what actually went over the inner transport, dropped messages never count as sent
+    fn stats(&self) -> TransportStats {
+        self.inner.stats()
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::time::Duration;
+use anyhow::{bail, Result};
+use crate::protocol::{self, Message};
+use crate::transport::{ConnectionStatus, Transport, TransportStats};
+
+// both ends of an in process connection, dropping one disconnects the other
+pub struct MemoryTransport {
+    outgoing: Sender<Message>,
+    incoming: Receiver<Message>,
+    status: ConnectionStatus,
+    // counts the bytes the messages would take on a real wire
+    stats: TransportStats,
+}
+impl MemoryTransport {
+    pub fn pair() -> (Self, Self) {
+        let (a_sender, b_receiver) = mpsc::channel();
+        let (b_sender, a_receiver) = mpsc::channel();
+        (
+            Self { outgoing: a_sender, incoming: a_receiver, status: ConnectionStatus::Connected, stats: TransportStats::default() },
+            Self { outgoing: b_sender, incoming: b_receiver, status: ConnectionStatus::Connected, stats: TransportStats::default() },
+        )
+    }
+
+    fn disconnected<T>(&mut self) -> Result<T> {
+        self.status = ConnectionStatus::Disconnected;
+        bail!("the other end of the memory transport was dropped")
+    }
+}
+impl Transport for MemoryTransport {
+    fn send(&mut self, message: &Message) -> Result<()> {
+        // refuses what a real transport couldn't send either
+        let len = protocol::encode(message)?.len();
+        if self.outgoing.send(message.clone()).is_err() {
+            return self.disconnected();
+        }
+        self.stats.record_sent(len);
+        Ok(())
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Message>> {
+        let received = if timeout.is_zero() {
+            match self.incoming.try_recv() {
+                Ok(message) => Ok(Some(message)),
+                Err(TryRecvError::Empty) => Ok(None),
+                Err(TryRecvError::Disconnected) => self.disconnected(),
+            }
+        } else {
+            match self.incoming.recv_timeout(timeout) {
+                Ok(message) => Ok(Some(message)),
+                Err(RecvTimeoutError::Timeout) => Ok(None),
+                Err(RecvTimeoutError::Disconnected) => self.disconnected(),
+            }
+        };
+        if let Ok(Some(message)) = &received {
+            self.stats.record_received(protocol::encode(message).map_or(0, |frame| frame.len()));
+        }
+        received
+    }
+
+    fn status(&self) -> ConnectionStatus {
+        self.status
+    }
+
+    fn stats(&self) -> TransportStats {
+        self.stats
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt;
+use std::time::Duration;
+use anyhow::Result;
+use crate::protocol::Message;
+
+mod conditioner;
+mod memory;
+mod relay;
+mod reliable;
+mod tcp;
+mod udp;
+
+pub use conditioner::{ConditionedTransport, Conditioner, NetworkConditions};
+pub use memory::MemoryTransport;
+pub use relay::UdpRelay;
+pub use reliable::ReliableEndpoint;
+pub use tcp::TcpTransport;
+pub use udp::{UdpListener, UdpPeer, UdpTransport};
+
+const UDP_SCHEME: &str = "udp://";
+const TCP_SCHEME: &str = "tcp://";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ConnectionStatus {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+impl fmt::Display for ConnectionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionStatus::Connecting => write!(f, "connecting"),
+            ConnectionStatus::Connected => write!(f, "connected"),
+            ConnectionStatus::Disconnected => write!(f, "disconnected"),
+        }
+    }
+}
+
+// running totals since the transport was created. a packet is whatever the transport
+// puts on the wire in one go: a frame over tcp, a datagram over udp
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TransportStats {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub packets_sent: u64,
+    pub packets_received: u64,
+}
+impl TransportStats {
+    pub fn record_sent(&mut self, bytes: usize) {
+        self.bytes_sent += bytes as u64;
+        self.packets_sent += 1;
+    }
+
+    pub fn record_received(&mut self, bytes: usize) {
+        self.bytes_received += bytes as u64;
+        self.packets_received += 1;
+    }
+}
+impl std::ops::Add for TransportStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            bytes_sent: self.bytes_sent + other.bytes_sent,
+            bytes_received: self.bytes_received + other.bytes_received,
+            packets_sent: self.packets_sent + other.packets_sent,
+            packets_received: self.packets_received + other.packets_received,
+        }
+    }
+}
+impl std::ops::Sub for TransportStats {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            bytes_sent: self.bytes_sent.saturating_sub(other.bytes_sent),
+            bytes_received: self.bytes_received.saturating_sub(other.bytes_received),
+            packets_sent: self.packets_sent.saturating_sub(other.packets_sent),
+            packets_received: self.packets_received.saturating_sub(other.packets_received),
+        }
+    }
+}
+
+// moves whole protocol messages between two ends, the client network thread and
+// the server only ever see this so they work the same over sockets or in memory
+pub trait Transport: Send {
+    fn send(&mut self, message: &Message) -> Result<()>;
+
+    // waits at most `timeout` for the next message, Ok(None) if nothing arrived in time
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Message>>;
+
+    fn status(&self) -> ConnectionStatus;
+
+    fn stats(&self) -> TransportStats;
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&mut self, message: &Message) -> Result<()> {
+        (**self).send(message)
+    }
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Message>> {
+        (**self).receive(timeout)
+    }
+    fn status(&self) -> ConnectionStatus {
+        (**self).status()
+    }
+    fn stats(&self) -> TransportStats {
+        (**self).stats()
+    }
+}
+
+// "udp://host:port" picks udp, anything else (optionally "tcp://host:port") is tcp
+pub fn connect(host_addr: &str) -> Result<Box<dyn Transport>> {
+    if let Some(addr) = host_addr.strip_prefix(UDP_SCHEME) {
+        return Ok(Box::new(UdpTransport::connect(addr)?));
+    }
+    let addr = host_addr.strip_prefix(TCP_SCHEME).unwrap_or(host_addr);
+    Ok(Box::new(TcpTransport::connect(addr)?))
+}
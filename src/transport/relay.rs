@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use crate::transport::{Conditioner, NetworkConditions};
+
+const MAX_DATAGRAM_SIZE: usize = 65507;
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+// clients that stay silent this long lose their upstream socket
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct RelayClient {
+    upstream: UdpSocket,
+    to_server: Conditioner<Vec<u8>>,
+    to_client: Conditioner<Vec<u8>>,
+    last_active: Instant,
+}
+
+// forwards udp datagrams between clients and a server through the simulated network,
+// every client gets its own upstream socket so the server still sees one address per client
+pub struct UdpRelay {
+    socket: UdpSocket,
+    server: SocketAddr,
+    conditions: NetworkConditions,
+    clients: HashMap<SocketAddr, RelayClient>,
+}
+impl UdpRelay {
+    pub fn bind<A: ToSocketAddrs, S: ToSocketAddrs>(addr: A, server: S, conditions: NetworkConditions) -> Result<Self> {
+        let server = server.to_socket_addrs()?.next().ok_or_else(|| anyhow!("server address did not resolve"))?;
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            server,
+            conditions,
+            clients: HashMap::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    // blocks forever
+    pub fn run(mut self) -> Result<()> {
+        let mut datagram = [0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            let now = Instant::now();
+            while let Some((len, addr)) = nonblocking(self.socket.recv_from(&mut datagram))? {
+                let client = match self.clients.get_mut(&addr) {
+                    Some(client) => client,
+                    None => {
+                        log::info!("relaying {addr} to {}", self.server);
+                        let client = self.connect_upstream()?;
+                        self.clients.entry(addr).or_insert(client)
+                    }
+                };
+                client.last_active = now;
+                client.to_server.push(datagram[..len].to_vec(), now);
+            }
+
+            for (addr, client) in self.clients.iter_mut() {
+                while let Some(len) = nonblocking(client.upstream.recv(&mut datagram))? {
+                    client.to_client.push(datagram[..len].to_vec(), now);
+                }
+                while let Some(datagram) = client.to_server.pop_ready(now) {
+                    // the server being down is the server's problem, keep relaying
+                    let _ = client.upstream.send(&datagram);
+                }
+                while let Some(datagram) = client.to_client.pop_ready(now) {
+                    let _ = self.socket.send_to(&datagram, addr);
+                }
+            }
+            self.clients.retain(|addr, client| {
+                let active = now - client.last_active < IDLE_TIMEOUT;
+                if !active {
+                    log::info!("{addr} went idle");
+                }
+                active
+            });
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn connect_upstream(&self) -> Result<RelayClient> {
+        let local: SocketAddr = if self.server.is_ipv4() { "0.0.0.0:0".parse()? } else { "[::]:0".parse()? };
+        let upstream = UdpSocket::bind(local)?;
+        upstream.connect(self.server)?;
+        upstream.set_nonblocking(true)?;
+        Ok(RelayClient {
+            upstream,
+            to_server: Conditioner::new(self.conditions),
+            to_client: Conditioner::new(self.conditions),
+            last_active: Instant::now(),
+        })
+    }
+}
+
+// Ok(None) when a non blocking socket has nothing right now
+fn nonblocking<T>(result: std::io::Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(None),
+        // icmp errors from a peer that is gone, not a reason to stop relaying
+        Err(err) if matches!(err.kind(), ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
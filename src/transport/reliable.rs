@@ -0,0 +1,270 @@
+use std::collections::{HashMap, VecDeque};
+use std::mem::Discriminant;
+use std::time::{Duration, Instant};
+use anyhow::{bail, ensure, Result};
+use crate::protocol::{self, Message};
+
+// packet layout, all big endian:
+//   sequence u16, ack u16, ack bits u32, flags u8, message count u8
+// followed by every message as:
+//   kind u8, reliable message id u16 (only for reliable ones), protocol frame
+const RESEND_INTERVAL: Duration = Duration::from_millis(100);
+// acks ride on outgoing packets, if nothing went out for this long a bare ack is sent
+const ACK_INTERVAL: Duration = Duration::from_millis(50);
+// resends are only added to a packet while it stays under this size
+const MAX_PACKET_SIZE: usize = 1200;
+const SENT_PACKETS_TRACKED: usize = 256;
+// far more than a live peer ever leaves unacked or gets ahead by, beyond this it is gone or misbehaving
+const MAX_UNACKED: usize = 1024;
+const MAX_OUT_OF_ORDER: u16 = 1024;
+const HEADER_SIZE: usize = 10;
+// set once the sender has received anything, until then ack and ack bits mean nothing
+const FLAG_HAS_ACK: u8 = 1;
+
+const KIND_RELIABLE: u8 = 0;
+const KIND_UNRELIABLE_SEQUENCED: u8 = 1;
+
+// inputs and connection management must arrive, snapshots and acks are only
+// useful while they are the newest ones
+pub fn is_reliable(message: &Message) -> bool {
+    !matches!(message, Message::Snapshot { .. } | Message::InputAck { .. } | Message::Ping { .. } | Message::Pong { .. })
+}
+
+// true if `a` comes after `b`, taking wrap around into account
+fn sequence_greater_than(a: u16, b: u16) -> bool {
+    a != b && a.wrapping_sub(b) < u16::MAX / 2
+}
+
+struct PendingMessage {
+    id: u16,
+    frame: Vec<u8>,
+    last_sent: Option<Instant>,
+}
+
+// sequencing, acks and resends for one end of a udp conversation, without any io:
+// whatever it returns goes in a datagram, whatever arrives in a datagram goes to `receive`
+pub struct ReliableEndpoint {
+    next_sequence: u16,
+    // newest packet received and which of the 32 before it also arrived
+    remote_sequence: Option<u16>,
+    received_bits: u32,
+    ack_pending: bool,
+    last_sent: Option<Instant>,
+
+    next_message_id: u16,
+    unacked: VecDeque<PendingMessage>,
+    // reliable message ids carried by each sent packet, indexed by sequence
+    sent_packets: Vec<Option<(u16, Vec<u16>)>>,
+
+    next_expected_id: u16,
+    out_of_order: HashMap<u16, Message>,
+    // newest packet each kind of unreliable message came in, kinds don't go stale because of each other
+    last_unreliable: HashMap<Discriminant<Message>, u16>,
+    delivered: VecDeque<Message>,
+    // set once either limit above was hit, there is no recovering from that
+    overflowed: bool,
+}
+impl Default for ReliableEndpoint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl ReliableEndpoint {
+    pub fn new() -> Self {
+        Self {
+            next_sequence: 0,
+            remote_sequence: None,
+            received_bits: 0,
+            ack_pending: false,
+            last_sent: None,
+            next_message_id: 0,
+            unacked: VecDeque::new(),
+            sent_packets: vec![None; SENT_PACKETS_TRACKED],
+            next_expected_id: 0,
+            out_of_order: HashMap::new(),
+            last_unreliable: HashMap::new(),
+            delivered: VecDeque::new(),
+            overflowed: false,
+        }
+    }
+
+    // returns the packet to put on the wire
+    pub fn send(&mut self, message: &Message, now: Instant) -> Result<Vec<u8>> {
+        let frame = protocol::encode(message)?;
+        if is_reliable(message) {
+            if self.unacked.len() >= MAX_UNACKED {
+                self.overflowed = true;
+                bail!("{MAX_UNACKED} messages were never acked");
+            }
+            let id = self.next_message_id;
+            self.next_message_id = self.next_message_id.wrapping_add(1);
+            self.unacked.push_back(PendingMessage { id, frame, last_sent: None });
+            Ok(self.write_packet(None, now))
+        } else {
+            Ok(self.write_packet(Some(frame), now))
+        }
+    }
+
+    // a packet with due resends or a bare ack, call this regularly even when there is nothing to send
+    pub fn update(&mut self, now: Instant) -> Option<Vec<u8>> {
+        let resend_due = self.unacked.iter().any(|message| Self::is_due(message, now));
+        let ack_due = self.ack_pending && self.last_sent.is_none_or(|last| now - last >= ACK_INTERVAL);
+        if resend_due || ack_due {
+            Some(self.write_packet(None, now))
+        } else {
+            None
+        }
+    }
+
+    pub fn receive(&mut self, packet: &[u8]) -> Result<()> {
+        ensure!(packet.len() >= HEADER_SIZE, "packet is too short");
+        let sequence = u16::from_be_bytes([packet[0], packet[1]]);
+        let ack = u16::from_be_bytes([packet[2], packet[3]]);
+        let ack_bits = u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]);
+        let flags = packet[8];
+        let count = packet[9];
+
+        let mut messages = Vec::with_capacity(count as usize);
+        let mut rest = &packet[HEADER_SIZE..];
+        for _ in 0..count {
+            let (kind, body) = rest.split_first().ok_or_else(|| anyhow::anyhow!("packet is truncated"))?;
+            let (id, body) = match *kind {
+                KIND_RELIABLE => {
+                    ensure!(body.len() >= 2, "packet is truncated");
+                    (Some(u16::from_be_bytes([body[0], body[1]])), &body[2..])
+                }
+                KIND_UNRELIABLE_SEQUENCED => (None, body),
+                kind => bail!("unknown message kind {kind}"),
+            };
+            let Some((message, len)) = protocol::decode_frame(body)? else { bail!("packet is truncated") };
+            messages.push((id, message));
+            rest = &body[len..];
+        }
+        ensure!(rest.is_empty(), "{} trailing bytes after packet", rest.len());
+
+        self.record_received(sequence);
+        if flags & FLAG_HAS_ACK != 0 {
+            self.process_acks(ack, ack_bits);
+        }
+        for (id, message) in messages {
+            match id {
+                Some(id) => {
+                    let already_delivered = sequence_greater_than(self.next_expected_id, id);
+                    if !already_delivered && id.wrapping_sub(self.next_expected_id) >= MAX_OUT_OF_ORDER {
+                        self.overflowed = true;
+                        bail!("message {id} is too far ahead of {}, which never arrived", self.next_expected_id);
+                    }
+                    if !already_delivered {
+                        self.out_of_order.insert(id, message);
+                    }
+                }
+                None => {
+                    // stale if a newer one of the same kind was already delivered
+                    let last = self.last_unreliable.entry(std::mem::discriminant(&message)).or_insert(sequence.wrapping_sub(1));
+                    if sequence_greater_than(sequence, *last) {
+                        *last = sequence;
+                        self.delivered.push_back(message);
+                    }
+                }
+            }
+        }
+        while let Some(message) = self.out_of_order.remove(&self.next_expected_id) {
+            self.delivered.push_back(message);
+            self.next_expected_id = self.next_expected_id.wrapping_add(1);
+        }
+        Ok(())
+    }
+
+    pub fn next_message(&mut self) -> Option<Message> {
+        self.delivered.pop_front()
+    }
+
+    pub fn unacked_len(&self) -> usize {
+        self.unacked.len()
+    }
+
+    // too much piled up waiting for the peer, the connection is as good as lost
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    fn is_due(message: &PendingMessage, now: Instant) -> bool {
+        message.last_sent.is_none_or(|last| now - last >= RESEND_INTERVAL)
+    }
+
+    fn write_packet(&mut self, unreliable: Option<Vec<u8>>, now: Instant) -> Vec<u8> {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        let mut packet = Vec::with_capacity(MAX_PACKET_SIZE);
+        packet.extend_from_slice(&sequence.to_be_bytes());
+        packet.extend_from_slice(&self.remote_sequence.unwrap_or(0).to_be_bytes());
+        packet.extend_from_slice(&self.received_bits.to_be_bytes());
+        packet.push(if self.remote_sequence.is_some() { FLAG_HAS_ACK } else { 0 });
+        packet.push(0);
+
+        let mut count = 0u8;
+        if let Some(frame) = unreliable {
+            packet.push(KIND_UNRELIABLE_SEQUENCED);
+            packet.extend_from_slice(&frame);
+            count += 1;
+        }
+        let mut carried = Vec::new();
+        for message in self.unacked.iter_mut() {
+            if count == u8::MAX {
+                break;
+            }
+            if !Self::is_due(message, now) {
+                continue;
+            }
+            // the first message always goes out, however big it is
+            if count > 0 && packet.len() + 3 + message.frame.len() > MAX_PACKET_SIZE {
+                break;
+            }
+            packet.push(KIND_RELIABLE);
+            packet.extend_from_slice(&message.id.to_be_bytes());
+            packet.extend_from_slice(&message.frame);
+            message.last_sent = Some(now);
+            carried.push(message.id);
+            count += 1;
+        }
+        packet[HEADER_SIZE - 1] = count;
+        self.sent_packets[sequence as usize % SENT_PACKETS_TRACKED] = Some((sequence, carried));
+        self.ack_pending = false;
+        self.last_sent = Some(now);
+        packet
+    }
+
+    fn record_received(&mut self, sequence: u16) {
+        self.ack_pending = true;
+        let Some(remote) = self.remote_sequence else {
+            self.remote_sequence = Some(sequence);
+            return;
+        };
+        if sequence_greater_than(sequence, remote) {
+            let shift = sequence.wrapping_sub(remote) as u32;
+            self.received_bits = if shift > 32 { 0 } else { self.received_bits.checked_shl(shift).unwrap_or(0) | 1 << (shift - 1) };
+            self.remote_sequence = Some(sequence);
+        } else {
+            let behind = remote.wrapping_sub(sequence) as u32;
+            if (1..=32).contains(&behind) {
+                self.received_bits |= 1 << (behind - 1);
+            }
+        }
+    }
+
+    fn process_acks(&mut self, ack: u16, ack_bits: u32) {
+        let acked = std::iter::once(ack).chain(
+            (0..32).filter(|bit| ack_bits & (1 << bit) != 0).map(|bit| ack.wrapping_sub(bit + 1)),
+        );
+        for sequence in acked {
+            let slot = &mut self.sent_packets[sequence as usize % SENT_PACKETS_TRACKED];
+            // the slot may hold an older or newer packet that happens to share it
+            match slot {
+                Some((sent, _)) if *sent == sequence => {}
+                _ => continue,
+            }
+            let Some((_, ids)) = slot.take() else { continue };
+            self.unacked.retain(|message| !ids.contains(&message.id));
+        }
+    }
+}
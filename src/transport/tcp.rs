@@ -0,0 +1,105 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use anyhow::{bail, Result};
+use crate::protocol::{self, Message};
+use crate::transport::{ConnectionStatus, Transport, TransportStats};
+
+const WRITE_TIMEOUT: Duration = Duration::from_millis(200);
+const READ_CHUNK_SIZE: usize = 4096;
+
+pub struct TcpTransport {
+    stream: TcpStream,
+    // bytes of a frame that hasn't completely arrived yet
+    read_buffer: Vec<u8>,
+    status: ConnectionStatus,
+    stats: TransportStats,
+}
+impl TcpTransport {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Self::new(TcpStream::connect(addr)?)
+    }
+
+    pub fn new(stream: TcpStream) -> Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        Ok(Self {
+            stream,
+            read_buffer: Vec::new(),
+            status: ConnectionStatus::Connected,
+            stats: TransportStats::default(),
+        })
+    }
+
+    fn take_frame(&mut self) -> Result<Option<Message>> {
+        match protocol::decode_frame(&self.read_buffer) {
+            Ok(Some((message, len))) => {
+                self.read_buffer.drain(..len);
+                self.stats.record_received(len);
+                Ok(Some(message))
+            }
+            Ok(None) => Ok(None),
+            Err(err) => {
+                // there is no way to find the next frame boundary after a bad frame
+                self.status = ConnectionStatus::Disconnected;
+                Err(err)
+            }
+        }
+    }
+}
+impl Transport for TcpTransport {
+    fn send(&mut self, message: &Message) -> Result<()> {
+        // receive may have left the socket non blocking, which would break write_all halfway
+        self.stream.set_nonblocking(false)?;
+        let frame = protocol::encode(message)?;
+        match self.stream.write_all(&frame) {
+            Ok(()) => {
+                self.stats.record_sent(frame.len());
+                Ok(())
+            }
+            Err(err) => {
+                self.status = ConnectionStatus::Disconnected;
+                Err(err.into())
+            }
+        }
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Message>> {
+        if let Some(message) = self.take_frame()? {
+            return Ok(Some(message));
+        }
+        if timeout.is_zero() {
+            self.stream.set_nonblocking(true)?;
+        } else {
+            self.stream.set_nonblocking(false)?;
+            self.stream.set_read_timeout(Some(timeout))?;
+        }
+        let mut chunk = [0u8; READ_CHUNK_SIZE];
+        match self.stream.read(&mut chunk) {
+            Ok(0) => {
+                self.status = ConnectionStatus::Disconnected;
+                bail!("connection closed by peer");
+            }
+            Ok(len) => self.read_buffer.extend_from_slice(&chunk[..len]),
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => return Ok(None),
+            Err(err) => {
+                self.status = ConnectionStatus::Disconnected;
+                return Err(err.into());
+            }
+        }
+        self.take_frame()
+    }
+
+    fn status(&self) -> ConnectionStatus {
+        self.status
+    }
+
+    fn stats(&self) -> TransportStats {
+        self.stats
+    }
+}
+impl Drop for TcpTransport {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
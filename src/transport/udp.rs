@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use crate::protocol::Message;
+use crate::transport::{ConnectionStatus, ReliableEndpoint, Transport, TransportStats};
+
+const MAX_DATAGRAM_SIZE: usize = 65507;
+// udp has no connection to lose, a peer that stays silent this long is considered gone
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+// every datagram is one packet of a ReliableEndpoint, so inputs arrive in order
+// while snapshots never wait for lost ones
+pub struct UdpTransport {
+    socket: UdpSocket,
+    endpoint: ReliableEndpoint,
+    status: ConnectionStatus,
+    last_received: Instant,
+    stats: TransportStats,
+}
+impl UdpTransport {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let addr = addr.to_socket_addrs()?.next().ok_or_else(|| anyhow!("address did not resolve"))?;
+        let local: SocketAddr = if addr.is_ipv4() { "0.0.0.0:0".parse()? } else { "[::]:0".parse()? };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+        Ok(Self {
+            socket,
+            endpoint: ReliableEndpoint::new(),
+            // until the server answers there is no telling whether anyone is listening
+            status: ConnectionStatus::Connecting,
+            last_received: Instant::now(),
+            stats: TransportStats::default(),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    fn send_packet(&mut self, packet: &[u8]) -> Result<()> {
+        match self.socket.send(packet) {
+            Ok(_) => {
+                self.stats.record_sent(packet.len());
+                Ok(())
+            }
+            Err(err) => self.disconnected(err.into()),
+        }
+    }
+
+    fn disconnected<T>(&mut self, err: anyhow::Error) -> Result<T> {
+        self.status = ConnectionStatus::Disconnected;
+        Err(err)
+    }
+}
+impl Transport for UdpTransport {
+    fn send(&mut self, message: &Message) -> Result<()> {
+        let packet = self.endpoint.send(message, Instant::now())?;
+        self.send_packet(&packet)
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Message>> {
+        if let Some(message) = self.endpoint.next_message() {
+            return Ok(Some(message));
+        }
+        if let Some(packet) = self.endpoint.update(Instant::now()) {
+            self.send_packet(&packet)?;
+        }
+        if timeout.is_zero() {
+            self.socket.set_nonblocking(true)?;
+        } else {
+            self.socket.set_nonblocking(false)?;
+            self.socket.set_read_timeout(Some(timeout))?;
+        }
+        let mut datagram = [0u8; MAX_DATAGRAM_SIZE];
+        match self.socket.recv(&mut datagram) {
+            Ok(len) => {
+                self.last_received = Instant::now();
+                self.stats.record_received(len);
+                self.status = ConnectionStatus::Connected;
+                self.endpoint.receive(&datagram[..len])?;
+                Ok(self.endpoint.next_message())
+            }
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                if self.last_received.elapsed() > PEER_TIMEOUT {
+                    return self.disconnected(anyhow!("server stopped answering"));
+                }
+                Ok(None)
+            }
+            Err(err) => self.disconnected(err.into()),
+        }
+    }
+
+    fn status(&self) -> ConnectionStatus {
+        if self.endpoint.overflowed() {
+            return ConnectionStatus::Disconnected;
+        }
+        self.status
+    }
+
+    fn stats(&self) -> TransportStats {
+        self.stats
+    }
+}
+
+// server side of udp, one socket shared by every client and demultiplexed by address
+pub struct UdpListener {
+    socket: Arc<UdpSocket>,
+    peers: HashMap<SocketAddr, Sender<Vec<u8>>>,
+}
+impl UdpListener {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Ok(Self {
+            socket: Arc::new(UdpSocket::bind(addr)?),
+            peers: HashMap::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    // blocks forever, every address that sends a first datagram is handed to `accept`
+    // as a transport of its own, returning false from `accept` stops the listener
+    pub fn run(mut self, mut accept: impl FnMut(UdpPeer) -> bool) -> Result<()> {
+        let mut datagram = [0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            let (len, addr) = match self.socket.recv_from(&mut datagram) {
+                Ok(received) => received,
+                // icmp errors from peers that went away show up here on some platforms
+                Err(err) if err.kind() == ErrorKind::ConnectionReset => continue,
+                Err(err) => return Err(err.into()),
+            };
+            let datagram = datagram[..len].to_vec();
+            // a peer whose transport was dropped starts over as a new one
+            let datagram = match self.peers.get(&addr) {
+                Some(peer) => match peer.send(datagram) {
+                    Ok(()) => continue,
+                    Err(mpsc::SendError(datagram)) => datagram,
+                },
+                None => datagram,
+            };
+            let (sender, datagrams) = mpsc::channel();
+            sender.send(datagram)?;
+            self.peers.insert(addr, sender);
+            let peer = UdpPeer {
+                socket: Arc::clone(&self.socket),
+                addr,
+                datagrams,
+                endpoint: ReliableEndpoint::new(),
+                status: ConnectionStatus::Connected,
+                last_received: Instant::now(),
+                stats: TransportStats::default(),
+            };
+            if !accept(peer) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+pub struct UdpPeer {
+    socket: Arc<UdpSocket>,
+    addr: SocketAddr,
+    datagrams: Receiver<Vec<u8>>,
+    endpoint: ReliableEndpoint,
+    status: ConnectionStatus,
+    last_received: Instant,
+    stats: TransportStats,
+}
+impl UdpPeer {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    fn send_packet(&mut self, packet: &[u8]) -> Result<()> {
+        match self.socket.send_to(packet, self.addr) {
+            Ok(_) => {
+                self.stats.record_sent(packet.len());
+                Ok(())
+            }
+            Err(err) => self.disconnected(err.into()),
+        }
+    }
+
+    fn disconnected<T>(&mut self, err: anyhow::Error) -> Result<T> {
+        self.status = ConnectionStatus::Disconnected;
+        Err(err)
+    }
+}
+impl Transport for UdpPeer {
+    fn send(&mut self, message: &Message) -> Result<()> {
+        let packet = self.endpoint.send(message, Instant::now())?;
+        self.send_packet(&packet)
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Message>> {
+        if let Some(message) = self.endpoint.next_message() {
+            return Ok(Some(message));
+        }
+        if let Some(packet) = self.endpoint.update(Instant::now()) {
+            self.send_packet(&packet)?;
+        }
+        let datagram = if timeout.is_zero() {
+            match self.datagrams.try_recv() {
+                Ok(datagram) => Some(datagram),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return self.disconnected(anyhow!("udp listener stopped")),
+            }
+        } else {
+            match self.datagrams.recv_timeout(timeout) {
+                Ok(datagram) => Some(datagram),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return self.disconnected(anyhow!("udp listener stopped")),
+            }
+        };
+        match datagram {
+            Some(datagram) => {
+                self.last_received = Instant::now();
+                self.stats.record_received(datagram.len());
+                self.endpoint.receive(&datagram)?;
+                Ok(self.endpoint.next_message())
+            }
+            None if self.last_received.elapsed() > PEER_TIMEOUT => self.disconnected(anyhow!("client stopped sending")),
+            None => Ok(None),
+        }
+    }
+
+    fn status(&self) -> ConnectionStatus {
+        if self.endpoint.overflowed() {
+            return ConnectionStatus::Disconnected;
+        }
+        self.status
+    }
+
+    fn stats(&self) -> TransportStats {
+        self.stats
+    }
+}
@@ -13,4 +13,21 @@ impl Vertex {
             attributes: ATTRIBS,
         }
     }
-}
\ No newline at end of file
+}
+// for textured pipelines, uv 0,0 is the top left of the texture
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TexturedVertex {
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+}
+impl TexturedVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBS: &[wgpu::VertexAttribute; 2] = &wgpu::vertex_attr_array![0=>Float32x3, 1=> Float32x2];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TexturedVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: ATTRIBS,
+        }
+    }
+}
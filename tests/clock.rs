@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+use multiplayer_game_player_test::clock::{NetworkClock, PING_INTERVAL};
+use multiplayer_game_player_test::protocol::Message;
+
+// the pretend server's clock runs this far ahead of the client's
+const SERVER_AHEAD_MS: u64 = 5000;
+
+// pings a server that answers halfway through `rtt`, returns the timestamp of the ping and when the pong arrived
+fn round_trip(clock: &mut NetworkClock, sent_at: Instant, rtt: Duration) -> (u64, Instant) {
+    let Some(Message::Ping { timestamp }) = clock.ping_due(sent_at) else { panic!("no ping was due") };
+    let server_time = timestamp + SERVER_AHEAD_MS + rtt.as_millis() as u64 / 2;
+    let received_at = sent_at + rtt;
+    clock.on_pong(timestamp, server_time, received_at);
+    (timestamp, received_at)
+}
+
+#[test]
+fn nothing_is_known_before_a_pong() {
+    let mut clock = NetworkClock::new();
+    let now = Instant::now();
+    assert!(clock.ping_due(now).is_some());
+    assert!(clock.ping_due(now + PING_INTERVAL / 2).is_none());
+    assert!(clock.ping_due(now + PING_INTERVAL).is_some());
+    assert_eq!(clock.rtt(), None);
+    assert_eq!(clock.server_time(now), None);
+}
+
+#[test]
+fn rtt_converges_and_offset_is_estimated() {
+    let mut clock = NetworkClock::new();
+    let start = Instant::now();
+    // a slow first answer, then a steady 40ms
+    round_trip(&mut clock, start, Duration::from_millis(80));
+    assert_eq!(clock.rtt().map(|rtt| rtt.as_millis()), Some(80));
+    let mut last = (0, start);
+    for i in 1..40 {
+        last = round_trip(&mut clock, start + PING_INTERVAL * i, Duration::from_millis(40));
+    }
+    let rtt = clock.rtt().unwrap().as_secs_f64() * 1000.0;
+    assert!((rtt - 40.0).abs() < 2.0, "rtt is {rtt}ms");
+    assert!(clock.jitter() < Duration::from_millis(5), "jitter is {:?}", clock.jitter());
+
+    // pings only carry whole milliseconds, so the estimate can be a little off
+    let (timestamp, received_at) = last;
+    let expected = (timestamp + 40 + SERVER_AHEAD_MS) as f64;
+    let estimated = clock.server_time(received_at).unwrap();
+    assert!((estimated - expected).abs() < 2.0, "server time is {estimated}, expected {expected}");
+    let later = received_at + Duration::from_secs(1);
+    assert!((clock.server_time(later).unwrap() - estimated - 1000.0).abs() < 1e-6);
+}
@@ -0,0 +1,38 @@
+use multiplayer_game_player_test::console::ConsoleCommand;
+
+fn error(line: &str) -> String {
+    ConsoleCommand::parse(line).unwrap_err().to_string()
+}
+
+#[test]
+fn connect() {
+    assert_eq!(ConsoleCommand::parse("connect 127.0.0.1:7878").unwrap(), ConsoleCommand::Connect("127.0.0.1:7878".to_string()));
+    assert_eq!(ConsoleCommand::parse("  connect   example.com:1 ").unwrap(), ConsoleCommand::Connect("example.com:1".to_string()));
+}
+
+#[test]
+fn nicknames_keep_their_spaces() {
+    assert_eq!(ConsoleCommand::parse("name bob").unwrap(), ConsoleCommand::Name("bob".to_string()));
+    assert_eq!(ConsoleCommand::parse(" name  big  bob ").unwrap(), ConsoleCommand::Name("big  bob".to_string()));
+}
+
+#[test]
+fn colors_are_three_bytes() {
+    assert_eq!(ConsoleCommand::parse("color 0 128 255").unwrap(), ConsoleCommand::Color([0, 128, 255]));
+    let usage = "usage: color <red> <green> <blue>, each 0 to 255";
+    assert_eq!(error("color 0 128 256"), usage);
+    assert_eq!(error("color -1 0 0"), usage);
+    assert_eq!(error("color 1 2"), usage);
+    assert_eq!(error("color 1 2 3 4"), usage);
+    assert_eq!(error("color red green blue"), usage);
+}
+
+#[test]
+fn usage_errors() {
+    assert_eq!(error("connect"), "usage: connect <host:port>");
+    assert_eq!(error("connect a b"), "usage: connect <host:port>");
+    assert_eq!(error("name"), "usage: name <nickname>");
+    assert_eq!(error("name   "), "usage: name <nickname>");
+    assert_eq!(error("dance"), "unknown command dance");
+    assert_eq!(error("  "), "empty command");
+}
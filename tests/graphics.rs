@@ -0,0 +1,18 @@
+use wgpu::Backends;
+use multiplayer_game_player_test::graphics::{parse_backends, GraphicsConfig};
+
+#[test]
+fn defaults_to_primary_backends() {
+    let config = GraphicsConfig::default();
+    assert_eq!(config.backends, Backends::PRIMARY);
+    assert!(!config.force_fallback_adapter);
+}
+
+#[test]
+fn parses_backend_lists() {
+    assert_eq!(parse_backends("vulkan").unwrap(), Backends::VULKAN);
+    assert_eq!(parse_backends("Vulkan, gl").unwrap(), Backends::VULKAN | Backends::GL);
+    assert_eq!(parse_backends("dx12,").unwrap(), Backends::DX12);
+    assert!(parse_backends("directx").is_err());
+    assert!(parse_backends(" , ").is_err());
+}
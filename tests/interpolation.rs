@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+use multiplayer_game_player_test::interpolation::Interpolator;
+
+const DELAY: Duration = Duration::from_millis(100);
+const MAX_EXTRAPOLATION: Duration = Duration::from_millis(50);
+
+// two players, 1 walks along x between server times 1000 and 1100, 2 stands still
+fn interpolator(now: Instant) -> Interpolator<u32> {
+    let mut interpolator = Interpolator::new(DELAY, MAX_EXTRAPOLATION);
+    interpolator.push_snapshot(1000, now, [(1, [0.0, 0.0]), (2, [5.0, 5.0])]);
+    interpolator.push_snapshot(1100, now, [(1, [1.0, 0.0]), (2, [5.0, 5.0])]);
+    interpolator
+}
+
+fn position(interpolator: &mut Interpolator<u32>, now: Instant, server_now: f64, key: u32) -> Option<[f32; 2]> {
+    interpolator.positions(now, Some(server_now)).into_iter().find(|(id, _)| *id == key).map(|(_, position)| position)
+}
+
+#[test]
+fn interpolates_between_snapshots() {
+    let now = Instant::now();
+    let mut interpolator = interpolator(now);
+    // rendering at 1050, halfway between the two
+    assert_eq!(position(&mut interpolator, now, 1150.0, 1), Some([0.5, 0.0]));
+    assert_eq!(position(&mut interpolator, now, 1150.0, 2), Some([5.0, 5.0]));
+}
+
+#[test]
+fn extrapolation_is_clamped() {
+    let now = Instant::now();
+    let mut interpolator = interpolator(now);
+    // 25ms past the newest snapshot keeps going
+    assert_eq!(position(&mut interpolator, now, 1225.0, 1), Some([1.25, 0.0]));
+    // 200ms past it stops at max_extrapolation
+    assert_eq!(position(&mut interpolator, now, 1400.0, 1), Some([1.5, 0.0]));
+}
+
+#[test]
+fn drops_out_of_order_snapshots() {
+    let now = Instant::now();
+    let mut interpolator = interpolator(now);
+    interpolator.push_snapshot(1050, now, [(1, [9.0, 9.0]), (2, [9.0, 9.0])]);
+    interpolator.push_snapshot(1100, now, [(1, [9.0, 9.0])]);
+    assert_eq!(position(&mut interpolator, now, 1150.0, 1), Some([0.5, 0.0]));
+    assert_eq!(position(&mut interpolator, now, 1150.0, 2), Some([5.0, 5.0]));
+}
+
+#[test]
+fn forgets_players_missing_from_a_snapshot() {
+    let now = Instant::now();
+    let mut interpolator = interpolator(now);
+    interpolator.push_snapshot(1200, now, [(1, [2.0, 0.0])]);
+    assert_eq!(position(&mut interpolator, now, 1250.0, 2), None);
+    assert_eq!(position(&mut interpolator, now, 1250.0, 1), Some([1.5, 0.0]));
+}
+
+#[test]
+fn delay_can_change() {
+    let now = Instant::now();
+    let mut interpolator = interpolator(now);
+    interpolator.set_delay(Duration::from_millis(125));
+    assert_eq!(position(&mut interpolator, now, 1150.0, 1), Some([0.25, 0.0]));
+}
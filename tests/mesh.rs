@@ -0,0 +1,63 @@
+use std::f32::consts::PI;
+use multiplayer_game_player_test::mesh::{MeshData, Shape};
+use multiplayer_game_player_test::sprite;
+
+// signed, positive when counter clockwise
+fn triangle_areas(mesh: &MeshData) -> Vec<f32> {
+    mesh.indices.chunks(3)
+        .map(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize].position);
+            ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])) / 2.0
+        })
+        .collect()
+}
+
+fn area(shape: Shape) -> f32 {
+    let mesh = shape.mesh([255, 255, 255]);
+    let areas = triangle_areas(&mesh);
+    assert!(areas.iter().all(|area| *area > 0.0), "{shape:?} has triangles the pipeline would cull");
+    areas.iter().sum()
+}
+
+#[test]
+fn shapes_cover_their_area() {
+    // a fine circle is almost a circle, the old fan left a whole segment out
+    assert!((area(Shape::Circle { radius: 1.0, segments: 256 }) - PI).abs() < 1e-3);
+    let sides = 6.0;
+    let hexagon = sides / 2.0 * (2.0 * PI / sides).sin();
+    assert!((area(Shape::Polygon { radius: 1.0, sides: 6 }) - hexagon).abs() < 1e-5);
+    assert!((area(Shape::Rect { width: 2.0, height: 0.5 }) - 1.0).abs() < 1e-6);
+    assert!((area(Shape::Ring { inner_radius: 0.5, outer_radius: 1.0, segments: 256 }) - 0.75 * PI).abs() < 1e-3);
+    // a shaft a third as wide as the head, then a triangle as long as the head is wide
+    assert!((area(Shape::Arrow { length: 3.0, width: 1.0 }) - (2.0 / 3.0 + 0.5)).abs() < 1e-6);
+}
+
+#[test]
+fn meshes_take_the_given_color() {
+    let mesh = Shape::Rect { width: 1.0, height: 1.0 }.mesh([255, 0, 0]);
+    assert!(mesh.vertices.iter().all(|vertex| vertex.color == [1.0, 0.0, 0.0]));
+    let circle = Shape::Circle { radius: 1.0, segments: 40 }.mesh([255, 255, 255]);
+    assert_eq!((circle.vertices.len(), circle.indices.len()), (41, 120));
+}
+
+#[test]
+fn sprite_quads_face_the_camera() {
+    let quad = sprite::quad([2.0, 1.0]);
+    let [a, b, c] = [0, 1, 2].map(|i| quad[i].position);
+    assert!((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1]) > 0.0);
+    // the top of the texture ends up at the top of the world
+    assert_eq!((quad[3].position[1], quad[3].uv[1]), (0.5, 0.0));
+    assert_eq!((quad[0].position[1], quad[0].uv[1]), (-0.5, 1.0));
+}
+
+#[test]
+fn huge_shapes_still_fit_u16_indices() {
+    for shape in [
+        Shape::Ring { inner_radius: 0.5, outer_radius: 1.0, segments: u16::MAX },
+        Shape::Circle { radius: 1.0, segments: u16::MAX },
+    ] {
+        let mesh = shape.mesh([255, 255, 255]);
+        assert!(mesh.vertices.len() <= u16::MAX as usize + 1);
+        assert!(mesh.indices.iter().all(|index| (*index as usize) < mesh.vertices.len()), "{shape:?} points past its vertices");
+    }
+}
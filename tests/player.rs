@@ -0,0 +1,42 @@
+use anyhow::bail;
+use multiplayer_game_player_test::player::Player;
+use multiplayer_game_player_test::protocol::PlayerInfo;
+use multiplayer_game_player_test::sim::{self, Input, PlayerState};
+use multiplayer_game_player_test::transport::ConnectionStatus;
+
+// never gets through, but counts as connected so batches are kept until acked
+fn offline_player() -> Player {
+    let mut player = Player::with_connector(|| bail!("no server in this test"), PlayerInfo::default());
+    player.set_connection_status(ConnectionStatus::Connected);
+    player
+}
+
+fn hold(player: &mut Player, input: Input, ticks: usize) -> Vec<Input> {
+    player.input = input;
+    for _ in 0..ticks {
+        player.tick();
+    }
+    vec![input; ticks]
+}
+
+#[test]
+fn reconcile_replays_unacked_inputs_on_the_server_position() {
+    let mut player = offline_player();
+    hold(&mut player, Input { forward: true, ..Default::default() }, 3);
+    player.send_buffer();
+    let second = hold(&mut player, Input { right: true, ..Default::default() }, 2);
+    player.send_buffer();
+    let unsent = hold(&mut player, Input { left: true, ..Default::default() }, 1);
+    assert_eq!(player.unacked_len(), 2);
+
+    // the server put the player somewhere else than predicted after the first batch
+    let server = PlayerState { position: [1.0, -1.0] };
+    player.reconcile(0, server.position);
+    assert_eq!(player.unacked_len(), 1);
+    assert_eq!(player.position(), sim::replay(server, second.iter().chain(&unsent)).position);
+
+    player.reconcile(1, [0.0, 0.0]);
+    assert_eq!(player.unacked_len(), 0);
+    assert_eq!(player.position(), sim::replay(PlayerState::default(), &unsent).position);
+}
+
@@ -0,0 +1,91 @@
+use std::io::Cursor;
+use multiplayer_game_player_test::sim::Input;
+use multiplayer_game_player_test::protocol::{self, Message, PlayerInfo, PROTOCOL_VERSION};
+
+fn assert_golden(message: Message, golden: &[u8]) {
+    let frame = protocol::encode(&message).unwrap();
+    assert_eq!(frame, golden, "encoding of {message:?} changed");
+    assert_eq!(protocol::read_message(&mut Cursor::new(golden)).unwrap(), message);
+}
+
+#[test]
+fn hello() {
+    assert_eq!(PROTOCOL_VERSION, 6);
+    assert_golden(Message::Hello { version: 6 }, &[0, 0, 0, 3, 0, 0, 6]);
+}
+
+#[test]
+fn join_and_leave() {
+    let info = PlayerInfo { nickname: "ab".to_string(), color: [1, 2, 3] };
+    assert_golden(Message::Join { info: info.clone() }, &[0, 0, 0, 8, 8, 0, 2, b'a', b'b', 1, 2, 3]);
+    assert_golden(Message::Welcome { id: 0x0102 }, &[0, 0, 0, 5, 9, 0, 0, 1, 2]);
+    assert_golden(Message::PlayerJoined { id: 7, info }, &[0, 0, 0, 12, 10, 0, 0, 0, 7, 0, 2, b'a', b'b', 1, 2, 3]);
+    assert_golden(Message::PlayerLeft { id: 7 }, &[0, 0, 0, 5, 11, 0, 0, 0, 7]);
+}
+
+#[test]
+fn input_batch() {
+    assert_golden(
+        Message::InputBatch {
+            sequence: 0x01020304,
+            inputs: vec![
+                Input::default(),
+                Input { forward: true, ..Default::default() },
+                Input { backward: true, right: true, ..Default::default() },
+            ],
+        },
+        &[0, 0, 0, 10, 1, 1, 2, 3, 4, 0, 3, 0, 1, 0b1010],
+    );
+}
+
+#[test]
+fn input_ack() {
+    assert_golden(
+        Message::InputAck { sequence: 7, position: [1.0, -0.5] },
+        &[0, 0, 0, 13, 7, 0, 0, 0, 7, 0x3f, 0x80, 0, 0, 0xbf, 0, 0, 0],
+    );
+}
+
+#[test]
+fn snapshot() {
+    assert_golden(
+        Message::Snapshot { server_time: 0x0102, players: vec![(3, [1.0, -0.5])] },
+        &[0, 0, 0, 23, 2, 0, 0, 0, 0, 0, 0, 1, 2, 0, 1, 0, 0, 0, 3, 0x3f, 0x80, 0, 0, 0xbf, 0, 0, 0],
+    );
+}
+
+#[test]
+fn ping_pong() {
+    assert_golden(Message::Ping { timestamp: 0x0102 }, &[0, 0, 0, 9, 3, 0, 0, 0, 0, 0, 0, 1, 2]);
+    assert_golden(
+        Message::Pong { timestamp: 0x0102, server_time: 0x0304 },
+        &[0, 0, 0, 17, 4, 0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 0, 0, 0, 0, 3, 4],
+    );
+}
+
+#[test]
+fn disconnect_and_error() {
+    assert_golden(Message::Disconnect, &[0, 0, 0, 1, 5]);
+    assert_golden(Message::Error { message: "no".to_string() }, &[0, 0, 0, 5, 6, 0, 2, b'n', b'o']);
+}
+
+#[test]
+fn rejects_malformed_frames() {
+    assert!(protocol::decode(&[42]).is_err());
+    assert!(protocol::decode(&[0, 0]).is_err());
+    assert!(protocol::decode(&[5, 0]).is_err());
+    assert!(protocol::decode(&[1, 0, 0, 0, 0, 0, 1, 0b1_0000]).is_err());
+    assert!(protocol::read_message(&mut Cursor::new([0xff, 0xff, 0xff, 0xff])).is_err());
+}
+
+#[test]
+fn refuses_frames_the_other_end_would_reject() {
+    // well under u16::MAX players, but 12 bytes each is more than a frame holds
+    let players = |count: u32| (0..count).map(|id| (id, [0.0, 0.0])).collect();
+    let fits = (protocol::MAX_FRAME_LEN - 11) as u32 / 12;
+    let frame = protocol::encode(&Message::Snapshot { server_time: 0, players: players(fits) }).unwrap();
+    assert!(protocol::decode_frame(&frame).unwrap().is_some());
+    assert!(protocol::encode(&Message::Snapshot { server_time: 0, players: players(fits + 1) }).is_err());
+    assert!(protocol::encode(&Message::Error { message: "x".repeat(u16::MAX as usize + 1) }).is_err());
+    assert!(protocol::write_message(&mut Vec::new(), &Message::InputBatch { sequence: 0, inputs: vec![Input::default(); 70_000] }).is_err());
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+use image::RgbaImage;
+use multiplayer_game_player_test::camera::Camera;
+use multiplayer_game_player_test::graphics::GraphicsConfig;
+use multiplayer_game_player_test::instance::Instance;
+use multiplayer_game_player_test::mesh::Shape;
+use multiplayer_game_player_test::scene::{Frame, ShapeBatch, WHITE};
+use multiplayer_game_player_test::screenshot::{self, headless_device, render_offscreen};
+
+const SIZE: u32 = 64;
+// adapters may round edges differently, anything further off than this is a real change
+const CHANNEL_TOLERANCE: u8 = 8;
+const MAX_DIFFERENT_PIXELS: usize = SIZE as usize;
+
+// any adapter will do, a software one if there is no other. None when there is none at all,
+// unless REQUIRE_GPU_TESTS is set, e.g. on ci where skipping would hide a broken renderer
+fn render(center: [f32; 2], frame: &Frame, player_sprite: Option<&Path>) -> Option<RgbaImage> {
+    let graphics = GraphicsConfig { backends: wgpu::Backends::all(), ..Default::default() };
+    let fallback = GraphicsConfig { force_fallback_adapter: true, ..graphics };
+    let device = pollster::block_on(headless_device(&graphics))
+        .or_else(|_| pollster::block_on(headless_device(&fallback)));
+    let (device, queue) = match device {
+        Ok(device) => device,
+        Err(err) if std::env::var_os("REQUIRE_GPU_TESTS").is_some() => panic!("nothing to render with: {err:#}"),
+        Err(err) => {
+            eprintln!("skipping, nothing to render with: {err:#}. set REQUIRE_GPU_TESTS=1 to fail instead");
+            return None;
+        }
+    };
+    let mut camera = Camera::new(SIZE, SIZE);
+    camera.follow_speed = None;
+    camera.follow(center, Duration::ZERO);
+    Some(render_offscreen(&device, &queue, &camera, frame, player_sprite, SIZE, SIZE).unwrap())
+}
+
+// set UPDATE_GOLDEN=1 to accept a new look, or to write a golden that doesn't exist yet
+fn assert_golden(image: RgbaImage, name: &str) {
+    let golden_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        screenshot::save_png(&image, &golden_path).unwrap();
+        return;
+    }
+    assert!(golden_path.exists(), "{} is missing, run with UPDATE_GOLDEN=1 to create it", golden_path.display());
+    let golden: RgbaImage = image::open(&golden_path).unwrap().into_rgba8();
+    assert_eq!(image.dimensions(), golden.dimensions());
+    let different = image.pixels().zip(golden.pixels())
+        .filter(|(a, b)| a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > CHANNEL_TOLERANCE))
+        .count();
+    assert!(different <= MAX_DIFFERENT_PIXELS, "{different} pixels differ from {}", golden_path.display());
+}
+
+#[test]
+fn players_match_golden_image() {
+    let instances = [
+        Instance::new([0.0, 0.0], [255, 0, 255]),
+        Instance::new([0.5, 0.5], [0, 255, 0]),
+        Instance::new([0.5, -0.5], [255, 255, 255]),
+    ];
+    if let Some(image) = render([0.25, 0.0], &Frame { players: &instances, shapes: &[] }, None) {
+        assert_golden(image, "players.png");
+    }
+}
+
+#[test]
+fn shapes_match_golden_image() {
+    let white = [Instance::new([-0.5, 0.5], WHITE), Instance::new([-0.5, -0.5], WHITE)];
+    let batch = |shape, color, position| (shape, color, [Instance::new(position, WHITE)]);
+    let shapes = [
+        batch(Shape::Rect { width: 0.4, height: 0.2 }, [255, 0, 0], [0.0, 0.5]),
+        batch(Shape::Polygon { radius: 0.2, sides: 3 }, [0, 255, 0], [0.5, 0.5]),
+        batch(Shape::Ring { inner_radius: 0.1, outer_radius: 0.2, segments: 24 }, [0, 0, 255], [0.0, -0.5]),
+        batch(Shape::Arrow { length: 0.5, width: 0.2 }, [255, 255, 0], [0.5, -0.5]),
+    ];
+    let mut batches: Vec<ShapeBatch> = shapes.iter()
+        .map(|(shape, color, instances)| ShapeBatch { shape: *shape, color: *color, instances })
+        .collect();
+    // two instances of one cached circle
+    batches.push(ShapeBatch { shape: Shape::Circle { radius: 0.2, segments: 32 }, color: [255, 128, 0], instances: &white });
+    if let Some(image) = render([0.0, 0.0], &Frame { players: &[], shapes: &batches }, None) {
+        assert_golden(image, "shapes.png");
+    }
+}
+
+#[test]
+fn player_sprites_load_from_disk() {
+    // a plain square covers the corners the bundled round sprite leaves clear
+    let path = std::env::temp_dir().join(format!("player-sprite-{}.png", std::process::id()));
+    screenshot::save_png(&RgbaImage::from_pixel(4, 4, image::Rgba([255, 255, 255, 255])), &path).unwrap();
+    let players = [Instance::new([0.0, 0.0], [255, 0, 0])];
+    let frame = Frame { players: &players, shapes: &[] };
+    let red = |image: &RgbaImage| image.pixels().filter(|pixel| pixel.0 == [255, 0, 0, 255]).count();
+    let rendered = (render([0.0, 0.0], &frame, None), render([0.0, 0.0], &frame, Some(&path)));
+    std::fs::remove_file(&path).unwrap();
+    let (Some(bundled), Some(square)) = rendered else { return };
+    assert!(red(&square) > red(&bundled), "{} red pixels with the square, {} with the bundled sprite", red(&square), red(&bundled));
+}
+
+#[test]
+fn screenshot_names_are_utc_timestamps() {
+    assert_eq!(screenshot::file_name(UNIX_EPOCH), "screenshot-1970-01-01_00-00-00.000.png");
+    let time = UNIX_EPOCH + Duration::from_millis(1_689_962_649_123);
+    assert_eq!(screenshot::file_name(time), "screenshot-2023-07-21_18-04-09.123.png");
+    let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400);
+    assert_eq!(screenshot::file_name(leap_day), "screenshot-2000-02-29_00-00-00.000.png");
+}
@@ -0,0 +1,46 @@
+use multiplayer_game_player_test::sim::{self, Input, PlayerState, MOVEMENT_SPEED};
+
+// a few seconds of every key combination, including opposing keys
+fn inputs() -> Vec<Input> {
+    (0..600u32)
+        .map(|tick| Input::from_byte(((tick / 7) % 16) as u8).unwrap())
+        .collect()
+}
+
+fn bits(position: [f32; 2]) -> [u32; 2] {
+    position.map(f32::to_bits)
+}
+
+#[test]
+fn same_inputs_same_state() {
+    let start = PlayerState { position: [0.3, -0.7] };
+    let (a, b) = (sim::replay(start, &inputs()), sim::replay(start, &inputs()));
+    assert_eq!(bits(a.position), bits(b.position));
+    assert_ne!(a, start);
+}
+
+#[test]
+fn prediction_matches_the_server() {
+    // the client predicts a tick at a time, the server replays whole batches like sim::replay does
+    let predicted = inputs().into_iter().fold(PlayerState::default(), |player, input| player.step(input));
+    let served = inputs().chunks(8).fold(PlayerState::default(), sim::replay);
+    assert_eq!(bits(predicted.position), bits(served.position));
+}
+
+#[test]
+fn diagonals_are_not_faster() {
+    let diagonal = PlayerState::default().step(Input { forward: true, right: true, ..Default::default() });
+    let [x, y] = diagonal.position;
+    assert!(((x * x + y * y).sqrt() - MOVEMENT_SPEED).abs() < 1e-6);
+    let opposing = PlayerState::default().step(Input { forward: true, backward: true, ..Default::default() });
+    assert_eq!(opposing.position, [0.0, 0.0]);
+}
+
+#[test]
+fn input_byte_round_trips() {
+    for byte in 0..16 {
+        assert_eq!(Input::from_byte(byte).unwrap().to_byte(), byte);
+    }
+    assert_eq!(Input::from_byte(Input::FORWARD | Input::LEFT), Some(Input { forward: true, left: true, ..Default::default() }));
+    assert_eq!(Input::from_byte(1 << 4), None);
+}
@@ -0,0 +1,371 @@
+use std::time::{Duration, Instant};
+use anyhow::anyhow;
+use multiplayer_game_player_test::network::NetworkEvent;
+use multiplayer_game_player_test::player::Player;
+use multiplayer_game_player_test::sim::{Input, PlayerState, MOVEMENT_SPEED, TICK_DURATION};
+use multiplayer_game_player_test::protocol::{Message, PlayerInfo, PROTOCOL_VERSION};
+use multiplayer_game_player_test::server::Server;
+use multiplayer_game_player_test::transport::{ConditionedTransport, ConnectionStatus, MemoryTransport, NetworkConditions, ReliableEndpoint, TcpTransport, Transport, UdpRelay, UdpTransport};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn join(transport: &mut dyn Transport, nickname: &str) {
+    transport.send(&Message::Hello { version: PROTOCOL_VERSION }).unwrap();
+    transport.send(&Message::Join { info: PlayerInfo { nickname: nickname.to_string(), color: [1, 2, 3] } }).unwrap();
+}
+
+// waits for the first message `pick` accepts, skipping everything else
+fn wait_for<T>(transport: &mut dyn Transport, mut pick: impl FnMut(Message) -> Option<T>) -> T {
+    let deadline = Instant::now() + TIMEOUT;
+    while Instant::now() < deadline {
+        if let Some(found) = transport.receive(Duration::from_millis(10)).unwrap().and_then(&mut pick) {
+            return found;
+        }
+    }
+    panic!("nothing expected arrived from the server");
+}
+
+// joins, moves forward once and waits for the server to ack it
+fn play(transport: &mut dyn Transport) {
+    join(transport, "player");
+    transport.send(&Message::InputBatch { sequence: 0, inputs: vec![Input { forward: true, ..Default::default() }] }).unwrap();
+    let deadline = Instant::now() + TIMEOUT;
+    while Instant::now() < deadline {
+        if let Some(Message::InputAck { sequence, position }) = transport.receive(Duration::from_millis(10)).unwrap() {
+            assert_eq!(sequence, 0);
+            assert_eq!(position, [0.0, 0.05]);
+            assert_eq!(transport.status(), ConnectionStatus::Connected);
+            return;
+        }
+    }
+    panic!("no ack from the server");
+}
+
+// a player that joins `server` over a fresh MemoryTransport pair on every connect,
+// seen through `conditions` from the player's side
+fn memory_player(server: &Server, conditions: NetworkConditions) -> Player {
+    let connector = server.connector();
+    let mut seed = 0;
+    let connect = move || {
+        let (client, server_end) = MemoryTransport::pair();
+        connector.send(Box::new(server_end)).map_err(|_| anyhow!("the server is gone"))?;
+        seed += 1;
+        Ok(Box::new(ConditionedTransport::with_seed(client, conditions, seed)) as Box<dyn Transport>)
+    };
+    Player::with_connector(connect, PlayerInfo { nickname: "player".to_string(), color: [1, 2, 3] })
+}
+
+// handles network events the way the game does and hands them back
+fn handle_events(player: &mut Player) -> Vec<NetworkEvent> {
+    let events: Vec<NetworkEvent> = player.network_events().collect();
+    for event in &events {
+        match *event {
+            NetworkEvent::Connected => player.set_connection_status(ConnectionStatus::Connected),
+            NetworkEvent::Disconnected(_) => player.set_connection_status(ConnectionStatus::Disconnected),
+            NetworkEvent::Welcome { id } => player.set_id(id),
+            NetworkEvent::InputAck { sequence, position } => player.reconcile(sequence, position),
+            _ => {}
+        }
+    }
+    events
+}
+
+// until `done` is happy with an event
+fn drive(player: &mut Player, mut done: impl FnMut(&NetworkEvent) -> bool) {
+    let deadline = Instant::now() + TIMEOUT;
+    while Instant::now() < deadline {
+        if handle_events(player).iter().any(&mut done) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    panic!("the player never got what it waited for");
+}
+
+#[test]
+fn memory_pair_delivers_in_order() {
+    let (mut a, mut b) = MemoryTransport::pair();
+    a.send(&Message::Ping { timestamp: 1 }).unwrap();
+    a.send(&Message::Ping { timestamp: 2 }).unwrap();
+    assert_eq!(b.receive(Duration::ZERO).unwrap(), Some(Message::Ping { timestamp: 1 }));
+    assert_eq!(b.receive(Duration::ZERO).unwrap(), Some(Message::Ping { timestamp: 2 }));
+    assert_eq!(b.receive(Duration::ZERO).unwrap(), None);
+    // a ping is 13 bytes with its length prefix
+    assert_eq!((a.stats().packets_sent, a.stats().bytes_sent), (2, 26));
+    assert_eq!((b.stats().packets_received, b.stats().bytes_received), (2, 26));
+    drop(a);
+    assert!(b.receive(Duration::ZERO).is_err());
+    assert_eq!(b.status(), ConnectionStatus::Disconnected);
+}
+
+#[test]
+fn server_over_memory() {
+    let server = Server::new();
+    let (mut client, server_end) = MemoryTransport::pair();
+    server.connector().send(Box::new(server_end)).unwrap();
+    server.spawn();
+    play(&mut client);
+}
+
+#[test]
+fn player_over_memory() {
+    let server = Server::new();
+    let mut player = memory_player(&server, NetworkConditions::default());
+    server.spawn();
+    drive(&mut player, |event| matches!(event, NetworkEvent::Welcome { .. }));
+    player.input = Input { forward: true, ..Default::default() };
+    for _ in 0..3 {
+        player.tick();
+    }
+    player.send_buffer();
+    let expected = (0..3).fold(PlayerState::default(), |state, _| state.step(player.input));
+    drive(&mut player, |event| matches!(event, NetworkEvent::InputAck { sequence: 0, .. }));
+    assert_eq!(player.position(), expected.position);
+    assert_eq!(player.connection_status(), ConnectionStatus::Connected);
+}
+
+#[test]
+fn player_through_conditioned_transport() {
+    let server = Server::new();
+    let conditions = NetworkConditions { latency: Duration::from_millis(20), loss: 0.1, ..Default::default() };
+    let mut player = memory_player(&server, conditions);
+    server.spawn();
+    drive(&mut player, |event| matches!(event, NetworkEvent::Connected));
+
+    // a batch every tick until one makes it there and back. some never will, and a lost
+    // part of the handshake makes the server drop the player, who then joins again
+    player.input = Input { forward: true, ..Default::default() };
+    let started = Instant::now();
+    let mut sent = 0;
+    while started.elapsed() < TIMEOUT {
+        player.tick();
+        player.send_buffer();
+        sent += 1;
+        if handle_events(&mut player).iter().any(|event| matches!(event, NetworkEvent::InputAck { .. })) {
+            assert!(started.elapsed() >= 2 * conditions.latency);
+            assert!(player.unacked_len() < sent);
+            assert!(player.position()[1] > 0.0);
+            return;
+        }
+        std::thread::sleep(TICK_DURATION);
+    }
+    panic!("no ack made it through");
+}
+
+#[test]
+fn server_drops_clients_that_never_join() {
+    let mut server = Server::new();
+    server.handshake_timeout = Duration::from_millis(100);
+    let (mut silent, server_end) = MemoryTransport::pair();
+    server.connector().send(Box::new(server_end)).unwrap();
+    let (mut halfway, server_end) = MemoryTransport::pair();
+    server.connector().send(Box::new(server_end)).unwrap();
+    server.spawn();
+    halfway.send(&Message::Hello { version: PROTOCOL_VERSION }).unwrap();
+    for client in [&mut silent, &mut halfway] {
+        assert!(matches!(wait_for(client, Some), Message::Error { .. }));
+        // the server dropped its end
+        assert!(client.receive(TIMEOUT).is_err());
+    }
+}
+
+#[test]
+fn server_answers_pings_with_its_clock() {
+    let server = Server::new();
+    let (mut client, server_end) = MemoryTransport::pair();
+    server.connector().send(Box::new(server_end)).unwrap();
+    server.spawn();
+    join(&mut client, "pinger");
+    std::thread::sleep(Duration::from_millis(20));
+    client.send(&Message::Ping { timestamp: 42 }).unwrap();
+    let deadline = Instant::now() + TIMEOUT;
+    while Instant::now() < deadline {
+        if let Some(Message::Pong { timestamp, server_time }) = client.receive(Duration::from_millis(10)).unwrap() {
+            assert_eq!(timestamp, 42);
+            assert!(server_time >= 20);
+            return;
+        }
+    }
+    panic!("no pong from the server");
+}
+
+#[test]
+fn server_introduces_players_by_id() {
+    let server = Server::new();
+    let (mut alice, server_end) = MemoryTransport::pair();
+    server.connector().send(Box::new(server_end)).unwrap();
+    let (mut bob, server_end) = MemoryTransport::pair();
+    server.connector().send(Box::new(server_end)).unwrap();
+    server.spawn();
+
+    join(&mut alice, "alice");
+    let alice_id = wait_for(&mut alice, |message| match message {
+        Message::Welcome { id } => Some(id),
+        _ => None,
+    });
+    join(&mut bob, "  bob\n");
+    let bob_id = wait_for(&mut bob, |message| match message {
+        Message::Welcome { id } => Some(id),
+        _ => None,
+    });
+    assert_ne!(alice_id, bob_id);
+
+    let alice_info = PlayerInfo { nickname: "alice".to_string(), color: [1, 2, 3] };
+    let bob_info = PlayerInfo { nickname: "bob".to_string(), color: [1, 2, 3] };
+    assert_eq!(wait_for(&mut bob, |message| match message {
+        Message::PlayerJoined { id, info } => Some((id, info)),
+        _ => None,
+    }), (alice_id, alice_info));
+    assert_eq!(wait_for(&mut alice, |message| match message {
+        Message::PlayerJoined { id, info } => Some((id, info)),
+        _ => None,
+    }), (bob_id, bob_info));
+    let mut ids = wait_for(&mut alice, |message| match message {
+        Message::Snapshot { players, .. } if players.len() == 2 => Some(players.into_iter().map(|(id, _)| id).collect::<Vec<_>>()),
+        _ => None,
+    });
+    ids.sort();
+    assert_eq!(ids, [alice_id.min(bob_id), alice_id.max(bob_id)]);
+
+    drop(bob);
+    assert_eq!(wait_for(&mut alice, |message| match message {
+        Message::PlayerLeft { id } => Some(id),
+        _ => None,
+    }), bob_id);
+}
+
+#[test]
+fn server_drops_inputs_beyond_elapsed_ticks() {
+    let server = Server::new();
+    let (mut client, server_end) = MemoryTransport::pair();
+    server.connector().send(Box::new(server_end)).unwrap();
+    server.spawn();
+    join(&mut client, "speedy");
+    // a thousand ticks of walking, sent all at once
+    let inputs = vec![Input { forward: true, ..Default::default() }; 1000];
+    client.send(&Message::InputBatch { sequence: 0, inputs }).unwrap();
+    let position = wait_for(&mut client, |message| match message {
+        Message::InputAck { position, .. } => Some(position),
+        _ => None,
+    });
+    assert!(position[1] > 0.0);
+    assert!(position[1] < 100.0 * MOVEMENT_SPEED, "moved to {position:?}");
+}
+
+#[test]
+fn server_over_tcp_and_udp() {
+    let mut server = Server::new();
+    let tcp_addr = server.listen_tcp("127.0.0.1:0").unwrap();
+    let udp_addr = server.listen_udp("127.0.0.1:0").unwrap();
+    server.spawn();
+    play(&mut TcpTransport::connect(tcp_addr).unwrap());
+    play(&mut UdpTransport::connect(udp_addr).unwrap());
+}
+
+#[test]
+fn reliable_endpoint_survives_loss() {
+    let mut client = ReliableEndpoint::new();
+    let mut server = ReliableEndpoint::new();
+    let mut now = Instant::now();
+    let mut received = Vec::new();
+    // every other packet from the client is lost
+    for sequence in 0..10u32 {
+        let packet = client.send(&Message::InputBatch { sequence, inputs: vec![] }, now).unwrap();
+        if sequence % 2 == 0 {
+            server.receive(&packet).unwrap();
+        }
+    }
+    for _ in 0..10 {
+        now += Duration::from_millis(200);
+        if let Some(packet) = client.update(now) {
+            server.receive(&packet).unwrap();
+        }
+        if let Some(packet) = server.update(now) {
+            client.receive(&packet).unwrap();
+        }
+        while let Some(Message::InputBatch { sequence, .. }) = server.next_message() {
+            received.push(sequence);
+        }
+    }
+    assert_eq!(received, (0..10).collect::<Vec<_>>());
+    assert_eq!(client.unacked_len(), 0);
+
+    // a snapshot that arrives after a newer one is dropped
+    let old = server.send(&Message::Snapshot { server_time: 1, players: vec![] }, now).unwrap();
+    let new = server.send(&Message::Snapshot { server_time: 2, players: vec![] }, now).unwrap();
+    client.receive(&new).unwrap();
+    client.receive(&old).unwrap();
+    assert_eq!(client.next_message(), Some(Message::Snapshot { server_time: 2, players: vec![] }));
+    assert_eq!(client.next_message(), None);
+
+    // but an older ack still counts after a newer snapshot, they go stale separately
+    let ack = server.send(&Message::InputAck { sequence: 9, position: [0.0, 0.0] }, now).unwrap();
+    let snapshot = server.send(&Message::Snapshot { server_time: 3, players: vec![] }, now).unwrap();
+    client.receive(&snapshot).unwrap();
+    client.receive(&ack).unwrap();
+    assert_eq!(client.next_message(), Some(Message::Snapshot { server_time: 3, players: vec![] }));
+    assert_eq!(client.next_message(), Some(Message::InputAck { sequence: 9, position: [0.0, 0.0] }));
+    assert_eq!(client.next_message(), None);
+}
+
+#[test]
+fn reliable_endpoint_gives_up_on_peers_that_fall_behind() {
+    let now = Instant::now();
+    let batch = |sequence| Message::InputBatch { sequence, inputs: vec![] };
+
+    // nothing ever gets acked
+    let mut lonely = ReliableEndpoint::new();
+    let failed_at = (0..5000).find(|sequence| lonely.send(&batch(*sequence), now).is_err());
+    assert!(failed_at.is_some() && lonely.overflowed());
+
+    // the first message is lost for good while everything after it arrives and is acked
+    let (mut client, mut server) = (ReliableEndpoint::new(), ReliableEndpoint::new());
+    let mut failed_at = None;
+    for sequence in 0..5000 {
+        let packet = client.send(&batch(sequence), now).unwrap();
+        if sequence > 0 && server.receive(&packet).is_err() {
+            failed_at = Some(sequence);
+            break;
+        }
+        client.receive(&server.send(&Message::Ping { timestamp: 0 }, now).unwrap()).unwrap();
+    }
+    assert!(failed_at.is_some() && server.overflowed());
+    // just the lost one and the one the server refused
+    assert_eq!(client.unacked_len(), 2);
+    assert_eq!(server.next_message(), None);
+}
+
+#[test]
+fn conditioned_transport_delays_messages() {
+    let conditions = NetworkConditions { latency: Duration::from_millis(50), ..Default::default() };
+    let (a, mut b) = MemoryTransport::pair();
+    let mut a = ConditionedTransport::with_seed(a, conditions, 7);
+    let sent_at = Instant::now();
+    a.send(&Message::Ping { timestamp: 1 }).unwrap();
+    let mut received = None;
+    while received.is_none() && sent_at.elapsed() < TIMEOUT {
+        // the conditioned end only hands messages on while it is being polled
+        a.receive(Duration::from_millis(1)).unwrap();
+        received = b.receive(Duration::ZERO).unwrap();
+    }
+    assert_eq!(received, Some(Message::Ping { timestamp: 1 }));
+    assert!(sent_at.elapsed() >= conditions.latency);
+}
+
+#[test]
+fn udp_through_lossy_relay() {
+    let mut server = Server::new();
+    let udp_addr = server.listen_udp("127.0.0.1:0").unwrap();
+    server.spawn();
+    let conditions = NetworkConditions {
+        latency: Duration::from_millis(20),
+        jitter: Duration::from_millis(10),
+        loss: 0.3,
+        duplication: 0.1,
+        reordering: 0.1,
+    };
+    let relay = UdpRelay::bind("127.0.0.1:0", udp_addr, conditions).unwrap();
+    let relay_addr = relay.local_addr().unwrap();
+    std::thread::spawn(move || relay.run());
+    play(&mut UdpTransport::connect(relay_addr).unwrap());
+}